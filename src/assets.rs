@@ -1,5 +1,18 @@
-use crate::common::*;
+use crate::{
+    common::*,
+    mechanics::behaviour::BehaviourList,
+    objects::{
+        boss::BossList, crafting::RecipeBook, tutorial::TutorialScript, waves::WaveList,
+        weapon_def::WeaponList,
+    },
+};
+use bevy::{
+    asset::{Asset, AssetLoader, AssetPath, LoadContext, LoadedAsset},
+    utils::BoxedFuture,
+};
 use bevy_kira_audio::AudioSource;
+use serde::Deserialize;
+use std::marker::PhantomData;
 
 #[derive(Default)]
 pub struct MyAssets {
@@ -25,6 +38,37 @@ pub struct MyAssets {
     pub wpn_smg: Handle<AudioSource>,
     pub wpn_plasma: Handle<AudioSource>,
     pub ray_charge: Handle<AudioSource>,
+
+    // gameplay data
+    pub waves: Handle<WaveList>,
+    pub tutorial: Handle<TutorialScript>,
+    pub recipes: Handle<RecipeBook>,
+    pub weapons: Handle<WeaponList>,
+    pub behaviours: Handle<BehaviourList>,
+    pub bosses: Handle<BossList>,
+}
+
+/// Asset which is deserialized from RON file
+pub trait RonAsset: Asset + for<'de> Deserialize<'de> {
+    /// Full extensions, like "waves.ron"
+    const EXTENSIONS: &'static [&'static str];
+
+    /// Called after deserialization, returns assets it depends on
+    fn resolve(&mut self, _load_context: &mut LoadContext) -> Vec<AssetPath<'static>> {
+        vec![]
+    }
+}
+
+pub trait AppRonAsset {
+    /// Registers asset and its loader
+    fn add_ron_asset<T: RonAsset>(&mut self) -> &mut Self;
+}
+
+impl AppRonAsset for App {
+    fn add_ron_asset<T: RonAsset>(&mut self) -> &mut Self {
+        self.add_asset::<T>()
+            .add_asset_loader(RonAssetLoader::<T>(PhantomData))
+    }
 }
 
 //
@@ -34,10 +78,22 @@ pub struct MyAssetsPlugin;
 impl Plugin for MyAssetsPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<MyAssets>()
-            .add_startup_system(load_assets);
+            .add_startup_system(load_data);
+        if !app.is_headless() {
+            app.add_startup_system(load_assets);
+        }
     }
 }
 
+fn load_data(mut assets: ResMut<MyAssets>, server: Res<AssetServer>) {
+    assets.waves = server.load("waves/campaign.waves.ron");
+    assets.tutorial = server.load("waves/tutorial.tutorial.ron");
+    assets.recipes = server.load("crafting/weapons.recipes.ron");
+    assets.weapons = server.load("weapons/projectiles.weapons.ron");
+    assets.behaviours = server.load("ai/enemies.behaviours.ron");
+    assets.bosses = server.load("bosses/campaign.bosses.ron");
+}
+
 fn load_assets(mut assets: ResMut<MyAssets>, server: Res<AssetServer>) {
     assets.glow = server.load("sprites/glow.png");
 
@@ -61,3 +117,22 @@ fn load_assets(mut assets: ResMut<MyAssets>, server: Res<AssetServer>) {
     assets.wpn_plasma = server.load("sounds/world/plasma.ogg");
     assets.ray_charge = server.load("sounds/world/ray_charge.ogg");
 }
+
+struct RonAssetLoader<T>(PhantomData<fn() -> T>);
+
+impl<T: RonAsset> AssetLoader for RonAssetLoader<T> {
+    fn load<'a>(
+        &'a self, bytes: &'a [u8], load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let mut asset: T = ron::de::from_bytes(bytes)?;
+            let dependencies = asset.resolve(load_context);
+            load_context.set_default_asset(LoadedAsset::new(asset).with_dependencies(dependencies));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        T::EXTENSIONS
+    }
+}
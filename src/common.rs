@@ -1,10 +1,14 @@
 pub use crate::{
     assets::MyAssets,
-    control::time::GameTime,
+    control::{
+        rng::GameRng,
+        time::{AppGameStage, GameStage, GameTime},
+    },
     mechanics::physics::PhysicsType,
     objects::spawn::GameplayObject,
     present::depth::Depth,
     settings::Settings,
+    simulation::AppHeadless,
     utils::{bevy::*, bevy_egui::*, math::*, rust::*},
 };
 pub use bevy::{log, math::vec2, prelude::*, utils::HashMap};
@@ -1,3 +1,8 @@
+use super::{
+    menu::is_exit_menu,
+    replay::Replay,
+    time::{AppGameStage, GameStage},
+};
 use crate::common::*;
 use bevy::input::mouse::MouseWheel;
 use enum_map::{enum_map, Enum, EnumMap};
@@ -12,17 +17,19 @@ pub enum InputAction {
     MoveDown,
 
     Fire,
+    FireCharged,
     FireMega,
     ChangeWeapon,
     Craft,
 
-    CraftSelect1,
-    CraftSelect2,
-    CraftSelect3,
-    CraftSelect4,
+    CraftPrevious,
+    CraftNext,
+    CloseMenu,
+    Buy,
 
     UberCharge,
     Dash,
+    Punch,
     Respawn,
 }
 
@@ -35,17 +42,19 @@ impl InputAction {
             InputAction::MoveDown => "Move down",
 
             InputAction::Fire => "Fire",
+            InputAction::FireCharged => "Charge shot",
             InputAction::FireMega => "Fire Megagun",
             InputAction::ChangeWeapon => "Change weapon",
             InputAction::Craft => "Craft weapon",
 
-            InputAction::CraftSelect1 => "Select craft part 1",
-            InputAction::CraftSelect2 => "Select craft part 2",
-            InputAction::CraftSelect3 => "Select craft part 3",
-            InputAction::CraftSelect4 => "Select craft part 4",
+            InputAction::CraftPrevious => "Select previous recipe or item",
+            InputAction::CraftNext => "Select next recipe or item",
+            InputAction::CloseMenu => "Close craft menu",
+            InputAction::Buy => "Buy item in shop",
 
             InputAction::UberCharge => "Ubercharge",
             InputAction::Dash => "Dash",
+            InputAction::Punch => "Punch (deflects projectiles)",
             InputAction::Respawn => "Retry",
         }
     }
@@ -98,17 +107,19 @@ impl Default for InputMap {
                 MoveDown => (InputKey::Key(KeyCode::S), InputType::Hold),
 
                 InputAction::Fire => (InputKey::Button(MouseButton::Left), InputType::Click),
+                InputAction::FireCharged => (InputKey::Button(MouseButton::Left), InputType::Hold),
                 InputAction::FireMega => (InputKey::Button(MouseButton::Right), InputType::Click),
                 InputAction::ChangeWeapon => (InputKey::Key(KeyCode::F), InputType::Click),
                 InputAction::Craft => (InputKey::Key(KeyCode::C), InputType::Click),
 
-                InputAction::CraftSelect1 => (InputKey::Key(KeyCode::Key1), InputType::Click),
-                InputAction::CraftSelect2 => (InputKey::Key(KeyCode::Key2), InputType::Click),
-                InputAction::CraftSelect3 => (InputKey::Key(KeyCode::Key3), InputType::Click),
-                InputAction::CraftSelect4 => (InputKey::Key(KeyCode::Key4), InputType::Click),
+                InputAction::CraftPrevious => (InputKey::Key(KeyCode::Key1), InputType::Click),
+                InputAction::CraftNext => (InputKey::Key(KeyCode::Key2), InputType::Click),
+                InputAction::CloseMenu => (InputKey::Key(KeyCode::Escape), InputType::Click),
+                InputAction::Buy => (InputKey::Key(KeyCode::B), InputType::Click),
 
                 InputAction::UberCharge => (InputKey::Key(KeyCode::LShift), InputType::Click),
                 InputAction::Dash => (InputKey::Key(KeyCode::Space), InputType::Click),
+                InputAction::Punch => (InputKey::Key(KeyCode::Q), InputType::Click),
                 Respawn => (InputKey::Key(KeyCode::R), InputType::Click),
             },
         }
@@ -130,42 +141,74 @@ impl Plugin for InputPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<InputMap>()
             .init_resource::<InputLock>()
-            .add_event::<InputAction>()
-            .add_system_to_stage(CoreStage::PreUpdate, emit_action);
+            .add_game_event::<InputAction>()
+            .add_system_to_stage(CoreStage::PreUpdate, emit_action)
+            .add_game_system(GameStage::PreUpdate, emit_held_action);
     }
 }
 
+/// Clicks are checked every frame, so they aren't missed if there are no ticks during it
 fn emit_action(
     lock: Res<InputLock>, map: Res<InputMap>, mut actions: EventWriter<InputAction>,
     keys: Res<Input<KeyCode>>, buttons: Res<Input<MouseButton>>,
-    scroll_evr: EventReader<MouseWheel>,
+    scroll_evr: EventReader<MouseWheel>, replay: Res<Replay>,
 ) {
+    if replay.is_playing() {
+        return;
+    }
+
     for (action, (key, ty)) in map.map.iter() {
-        if match action {
-            InputAction::Craft
-            | InputAction::CraftSelect1
-            | InputAction::CraftSelect2
-            | InputAction::CraftSelect3
-            | InputAction::CraftSelect4 => lock.active && !lock.allow_craft,
-            _ => lock.active,
-        } {
+        if *ty != InputType::Click || is_locked(&lock, action) {
             continue;
         }
 
         let active = match key {
-            InputKey::Key(key) => match ty {
-                InputType::Click => keys.just_pressed(*key),
-                InputType::Hold => keys.pressed(*key),
-            },
-            InputKey::Button(button) => match ty {
-                InputType::Click => buttons.just_pressed(*button),
-                InputType::Hold => buttons.pressed(*button),
-            },
+            InputKey::Key(key) => keys.just_pressed(*key),
+            InputKey::Button(button) => buttons.just_pressed(*button),
         }
             // TODO: bad hack
-            || (action == InputAction::ChangeWeapon && !scroll_evr.is_empty());
+            || (action == InputAction::ChangeWeapon && !scroll_evr.is_empty())
+            // same keys as for the main menu, whatever the binding is
+            || (action == InputAction::CloseMenu && is_exit_menu(&keys));
         if active {
             actions.send(action)
         }
     }
 }
+
+/// Held actions are emitted every tick, so their effect doesn't depend on framerate
+fn emit_held_action(
+    lock: Res<InputLock>, map: Res<InputMap>, mut actions: EventWriter<InputAction>,
+    keys: Res<Input<KeyCode>>, buttons: Res<Input<MouseButton>>, replay: Res<Replay>,
+) {
+    if replay.is_playing() {
+        for action in replay.frame().iter().flat_map(|frame| &frame.actions) {
+            actions.send(*action)
+        }
+        return;
+    }
+
+    for (action, (key, ty)) in map.map.iter() {
+        if *ty != InputType::Hold || is_locked(&lock, action) {
+            continue;
+        }
+
+        let active = match key {
+            InputKey::Key(key) => keys.pressed(*key),
+            InputKey::Button(button) => buttons.pressed(*button),
+        };
+        if active {
+            actions.send(action)
+        }
+    }
+}
+
+fn is_locked(lock: &InputLock, action: InputAction) -> bool {
+    match action {
+        InputAction::Craft
+        | InputAction::CraftPrevious
+        | InputAction::CraftNext
+        | InputAction::CloseMenu => lock.active && !lock.allow_craft,
+        _ => lock.active,
+    }
+}
@@ -1,7 +1,15 @@
-use super::{input::InputMap, time::TimeMode};
-use crate::{common::*, objects::spawn::SpawnControl, present::camera::WindowInfo};
-use bevy::app::AppExit;
+use super::{input::InputMap, replay::Replay, time::TimeMode};
+use crate::{
+    common::*,
+    objects::{
+        editor::LevelEditor, history::RunHistory, save::RunSave, spawn::SpawnControl,
+        tutorial::TutorialState, waves::WaveList,
+    },
+    present::camera::WindowInfo,
+};
+use bevy::{app::AppExit, ecs::system::SystemParam};
 use bevy_egui::EguiSettings;
+use std::marker::PhantomData;
 
 #[derive(Default)]
 pub struct PlayNowHack(pub bool);
@@ -17,13 +25,33 @@ pub struct MenuPlugin;
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<MenuState>()
-            .init_resource::<PlayNowHack>()
-            .add_system(show_menu)
+            .init_resource::<PlayNowHack>();
+        if app.is_headless() {
+            return;
+        }
+        app.add_system(show_menu)
             .add_startup_system(setup)
             .add_startup_system(play_now_hack);
     }
 }
 
+#[derive(SystemParam)]
+struct RunControl<'w, 's> {
+    replay: ResMut<'w, Replay>,
+    save: ResMut<'w, RunSave>,
+    #[system_param(ignore)]
+    _unused: PhantomData<&'s ()>,
+}
+
+/// Read-only data shown in the menu
+#[derive(SystemParam)]
+struct InfoPanels<'w, 's> {
+    input_map: Res<'w, InputMap>,
+    history: Res<'w, RunHistory>,
+    #[system_param(ignore)]
+    _unused: PhantomData<&'s ()>,
+}
+
 #[derive(Default, PartialEq, Eq)]
 enum MenuState {
     None,
@@ -34,8 +62,10 @@ enum MenuState {
 fn show_menu(
     mut ctx: ResMut<EguiContext>, mut state: ResMut<MenuState>, keys: Res<Input<KeyCode>>,
     mut exit_app: EventWriter<AppExit>, mut spawn: ResMut<SpawnControl>, window: Res<WindowInfo>,
-    mut settings: ResMut<Settings>, input_map: Res<InputMap>, mut windows: ResMut<Windows>,
-    mut time_ctl: ResMut<TimeMode>,
+    mut settings: ResMut<Settings>, info: InfoPanels, mut windows: ResMut<Windows>,
+    mut time_ctl: ResMut<TimeMode>, mut tutorial: ResMut<TutorialState>,
+    mut editor: ResMut<LevelEditor>, assets: Res<MyAssets>, waves: Res<Assets<WaveList>>,
+    mut generator: ResMut<WaveGenerator>, mut runs: RunControl,
 ) {
     if is_exit_menu(&keys) && !time_ctl.craft_menu {
         match *state {
@@ -81,19 +111,66 @@ fn show_menu(
                                             *state = MenuState::None;
                                             spawn.despawn = Some(true);
                                         }
+                                        if spawn.waiting_for_next_wave
+                                            && !editor.is_active()
+                                            && !runs.replay.is_playing()
+                                            && ui.button("Save and exit").clicked()
+                                        {
+                                            runs.save.save()
+                                        }
                                         if ui.button("Exit to main menu").clicked() {
                                             spawn.despawn = Some(false)
                                         }
                                     } else {
+                                        if let Some(saved) = runs.save.saved() {
+                                            if ui
+                                                .button(format!(
+                                                    "Continue run (wave {})",
+                                                    saved.wave + 1
+                                                ))
+                                                .clicked()
+                                            {
+                                                *state = MenuState::None;
+                                                runs.save.resume();
+                                            }
+                                        }
                                         if ui.button("Play (with tutorial)").clicked() {
                                             *state = MenuState::None;
                                             spawn.despawn = Some(true);
-                                            spawn.tutorial = Some(0);
+                                            tutorial.start();
+                                            editor.close();
+                                            generator.endless = false;
                                         }
                                         if ui.button("Play (skip tutorial)").clicked() {
                                             *state = MenuState::None;
                                             spawn.despawn = Some(true);
-                                            spawn.tutorial = None;
+                                            tutorial.stop();
+                                            editor.close();
+                                            generator.endless = false;
+                                        }
+                                        if ui.button("Play (endless)").clicked() {
+                                            *state = MenuState::None;
+                                            spawn.despawn = Some(true);
+                                            tutorial.stop();
+                                            editor.close();
+                                            generator.endless = true;
+                                        }
+                                        if ui.button("Level editor").clicked() {
+                                            *state = MenuState::None;
+                                            spawn.despawn = Some(true);
+                                            tutorial.stop();
+                                            editor.open(
+                                                waves
+                                                    .get(&assets.waves)
+                                                    .cloned()
+                                                    .unwrap_or_default(),
+                                            );
+                                        }
+                                        if let Some(data) = runs.replay.last().cloned() {
+                                            if ui.button("Watch last run").clicked() {
+                                                *state = MenuState::None;
+                                                runs.replay.play(data);
+                                            }
                                         }
                                     }
                                     #[cfg(not(target_arch = "wasm32"))]
@@ -121,7 +198,8 @@ fn show_menu(
                                     ui.heading("CONTROLS");
 
                                     let mut help = vec![];
-                                    for (i, (action, (key, ty))) in input_map.map.iter().enumerate()
+                                    for (i, (action, (key, ty))) in
+                                        info.input_map.map.iter().enumerate()
                                     {
                                         help.push((
                                             action.description().to_string(),
@@ -157,6 +235,14 @@ fn show_menu(
                                     });
                                 });
                             });
+
+                            // leaderboard pane
+                            ui.vertical(|ui| {
+                                ui.group(|ui| {
+                                    ui.heading("HIGH SCORES");
+                                    info.history.menu(ui);
+                                });
+                            });
                         });
                     });
                 },
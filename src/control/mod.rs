@@ -2,6 +2,8 @@ use crate::common::*;
 
 pub mod input;
 pub mod menu;
+pub mod replay;
+pub mod rng;
 pub mod time;
 
 pub struct ControlPlugin;
@@ -10,6 +12,8 @@ impl Plugin for ControlPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(time::TimePlugin)
             .add_plugin(menu::MenuPlugin)
-            .add_plugin(input::InputPlugin);
+            .add_plugin(input::InputPlugin)
+            .add_plugin(rng::RngPlugin)
+            .add_plugin(replay::ReplayPlugin);
     }
 }
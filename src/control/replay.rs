@@ -0,0 +1,283 @@
+use super::{
+    input::InputAction,
+    time::{AppGameStage, GameStage, TimeMode},
+};
+use crate::{
+    common::*,
+    objects::{
+        editor::LevelEditor,
+        procedural::WaveGenerator,
+        save::{RunSave, SavedRun},
+        spawn::SpawnControl,
+        tutorial::TutorialState,
+    },
+    present::camera::WindowInfo,
+    settings::Difficulty,
+};
+use bevy::ecs::system::SystemParam;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+/// Everything required to reproduce a run
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReplayData {
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    pub tutorial: bool,
+    pub endless: bool,
+    /// Run was continued from that save
+    #[serde(default)]
+    pub resumed: Option<SavedRun>,
+    pub frames: Vec<ReplayFrame>,
+}
+
+/// Input for a single tick of the game
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    /// World position
+    pub cursor: Vec2,
+    pub actions: Vec<InputAction>,
+    /// Respawn was requested after this frame
+    pub respawn: bool,
+}
+
+/// Resource - records every run and plays recorded ones instead of live input
+#[derive(Default)]
+pub struct Replay {
+    state: ReplayState,
+    /// Frame being played during current tick
+    frame: Option<ReplayFrame>,
+    /// Most recent finished recording
+    last: Option<ReplayData>,
+    /// Playback which will start after current run is stopped
+    request: Option<ReplayData>,
+}
+
+#[derive(Default)]
+enum ReplayState {
+    #[default]
+    None,
+    Recording(ReplayData),
+    Playing {
+        data: ReplayData,
+        index: usize,
+    },
+    /// Playback ended, but the run continues with live input
+    Finished,
+}
+
+impl Replay {
+    #[cfg(not(target_arch = "wasm32"))]
+    const FILE: &'static str = "last_run.replay.ron";
+
+    /// Input of current tick, if it's played from replay
+    pub fn frame(&self) -> Option<&ReplayFrame> {
+        self.frame.as_ref()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        matches!(self.state, ReplayState::Playing { .. })
+    }
+
+    pub fn last(&self) -> Option<&ReplayData> {
+        self.last.as_ref()
+    }
+
+    /// Stops current run and starts playback
+    pub fn play(&mut self, data: ReplayData) {
+        self.request = Some(data)
+    }
+
+    pub fn to_ron(data: &ReplayData) -> String {
+        ron::ser::to_string_pretty(data, ron::ser::PrettyConfig::new().depth_limit(2)).unwrap()
+    }
+
+    pub fn from_ron(s: &str) -> Option<ReplayData> {
+        ron::from_str(s)
+            .map_err(|error| log::error!("Invalid replay - {}", error))
+            .ok()
+    }
+
+    pub fn load_file(path: &str) -> Option<ReplayData> {
+        match std::fs::read_to_string(path) {
+            Ok(s) => Self::from_ron(&s),
+            Err(error) => {
+                log::error!("Failed to read replay (file: \"{}\") - {}", path, error);
+                None
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save(data: &ReplayData) {
+        match std::fs::write(Self::FILE, Self::to_ron(data)) {
+            Ok(_) => log::info!("Saved replay to \"{}\"", Self::FILE),
+            Err(error) => log::error!(
+                "Failed to save replay (file: \"{}\") - {}",
+                Self::FILE,
+                error
+            ),
+        }
+    }
+}
+
+//
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Replay>();
+        // simulation must not overwrite saved replay
+        if app.is_headless() {
+            return;
+        }
+        app.add_startup_system(load_last)
+            .add_game_system(GameStage::First, next_frame)
+            .add_game_system(GameStage::Last, record_frame);
+    }
+}
+
+/// Is game running and not paused by the main menu
+fn is_simulated(spawn: &SpawnControl, time_mode: &TimeMode) -> bool {
+    spawn.is_game_running() && !time_mode.main_menu
+}
+
+fn load_last(mut replay: ResMut<Replay>) {
+    #[cfg(not(target_arch = "wasm32"))]
+    if replay.last.is_none() {
+        replay.last = std::fs::read_to_string(Replay::FILE)
+            .ok()
+            .and_then(|s| Replay::from_ron(&s));
+    }
+}
+
+fn next_frame(mut replay: ResMut<Replay>, spawn: Res<SpawnControl>, time_mode: Res<TimeMode>) {
+    let simulated = is_simulated(&spawn, &time_mode);
+    let replay = &mut *replay;
+
+    replay.frame = None;
+    if let ReplayState::Playing { data, index } = &mut replay.state {
+        if simulated {
+            replay.frame = data.frames.get(*index).cloned();
+            *index += 1;
+            if replay.frame.is_none() {
+                log::info!("Replay finished");
+                replay.state = ReplayState::Finished;
+            }
+        }
+    }
+}
+
+#[derive(SystemParam)]
+struct RunSettings<'w, 's> {
+    settings: ResMut<'w, Settings>,
+    tutorial: ResMut<'w, TutorialState>,
+    generator: ResMut<'w, WaveGenerator>,
+    editor: ResMut<'w, LevelEditor>,
+    rng: ResMut<'w, GameRng>,
+    save: ResMut<'w, RunSave>,
+    #[system_param(ignore)]
+    _unused: PhantomData<&'s ()>,
+}
+
+fn record_frame(
+    mut replay: ResMut<Replay>, mut spawn: ResMut<SpawnControl>, time_mode: Res<TimeMode>,
+    mut actions: EventReader<InputAction>, window: Res<WindowInfo>, mut run: RunSettings,
+) {
+    let actions: Vec<_> = actions.iter().copied().collect();
+    let replay = &mut *replay;
+
+    if !spawn.is_game_running() {
+        match std::mem::take(&mut replay.state) {
+            ReplayState::Recording(data) => {
+                // a run stopped before anything happened isn't worth keeping
+                if !data.frames.is_empty() && replay.request.is_none() {
+                    #[cfg(not(target_arch = "wasm32"))]
+                    Replay::save(&data);
+                    replay.last = Some(data);
+                }
+            }
+            // playback is waiting for the spawn
+            ReplayState::Playing { data, index: 0 } => {
+                replay.state = ReplayState::Playing { data, index: 0 }
+            }
+            _ => (),
+        }
+
+        if let Some(data) = replay.request.take() {
+            log::info!("Playing replay, seed {}", data.seed);
+            run.settings.run_difficulty = Some(data.difficulty);
+            if data.tutorial {
+                run.tutorial.start()
+            } else {
+                run.tutorial.stop()
+            }
+            run.generator.endless = data.endless;
+            run.editor.close();
+            run.rng.set_next_seed(data.seed);
+            if let Some(saved) = &data.resumed {
+                saved.configure(
+                    &mut run.settings,
+                    &mut run.tutorial,
+                    &mut run.generator,
+                    &mut run.editor,
+                    &mut run.rng,
+                );
+            }
+            run.save.set_pending(data.resumed.clone());
+
+            spawn.despawn = Some(true);
+            replay.state = ReplayState::Playing { data, index: 0 };
+        }
+        return;
+    }
+
+    // run must be stopped before playback
+    if replay.request.is_some() {
+        spawn.despawn = Some(false);
+        return;
+    }
+
+    let respawn = spawn.despawn == Some(true);
+    if is_simulated(&spawn, &time_mode) {
+        let frame = ReplayFrame {
+            cursor: window.cursor,
+            actions,
+            respawn: false,
+        };
+        match &mut replay.state {
+            // editor runs are not reproducible - waves change on the fly
+            ReplayState::None if !run.editor.is_active() => {
+                replay.state = ReplayState::Recording(ReplayData {
+                    seed: run.rng.seed(),
+                    difficulty: run.settings.difficulty(),
+                    tutorial: run.tutorial.is_active(),
+                    endless: run.generator.endless,
+                    resumed: run.save.current().cloned(),
+                    frames: vec![frame],
+                })
+            }
+            ReplayState::Recording(data) => data.frames.push(frame),
+            ReplayState::Playing { .. } => {
+                if replay
+                    .frame
+                    .as_ref()
+                    .map(|frame| frame.respawn)
+                    .unwrap_or(false)
+                {
+                    spawn.despawn.get_or_insert(true);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // respawn may be requested from the menu while game is paused,
+    // so it's attached to the last simulated frame
+    if let ReplayState::Recording(data) = &mut replay.state {
+        if let Some(frame) = data.frames.last_mut() {
+            frame.respawn |= respawn;
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use crate::common::*;
+use enum_map::{enum_map, Enum, EnumMap};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Resource - all randomness in the game.
+///
+/// Same seed and same input produce the same run, as long as gameplay streams are used
+/// only by gameplay systems. Everything which doesn't affect the outcome (effects, sounds)
+/// must use cosmetic stream, since it may be called different number of times.
+pub struct GameRng {
+    seed: u64,
+    /// Seed is set from command line and must not change
+    fixed_seed: bool,
+    /// Used once by the next run instead of a new one
+    next_seed: Option<u64>,
+
+    gameplay: EnumMap<RngStream, StdRng>,
+    cosmetic: StdRng,
+}
+
+/// Gameplay streams are separate, so order of the systems using them doesn't matter
+#[derive(Clone, Copy, Enum)]
+pub enum RngStream {
+    /// Wave contents
+    Spawn,
+    /// Dropped loot movement
+    Loot,
+    /// Enemy aiming errors
+    Aim,
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::new(rand::thread_rng().gen(), false)
+    }
+}
+
+impl GameRng {
+    pub fn with_seed(seed: u64) -> Self {
+        Self::new(seed, true)
+    }
+
+    fn new(seed: u64, fixed_seed: bool) -> Self {
+        let mut rng = Self {
+            seed,
+            fixed_seed,
+            next_seed: None,
+            gameplay: enum_map! { _ => StdRng::seed_from_u64(seed) },
+            cosmetic: StdRng::seed_from_u64(seed),
+        };
+        rng.restart();
+        rng
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Seed for the next run only, regardless of the fixed one
+    pub fn set_next_seed(&mut self, seed: u64) {
+        self.next_seed = Some(seed)
+    }
+
+    /// Selects new seed (unless it's fixed) and restarts all streams
+    pub fn new_run(&mut self) {
+        if let Some(seed) = self.next_seed.take() {
+            self.seed = seed;
+        } else if !self.fixed_seed {
+            self.seed = rand::thread_rng().gen();
+        }
+        self.restart();
+        log::info!("Run seed: {}", self.seed);
+    }
+
+    /// Sets all streams to the initial state
+    pub fn restart(&mut self) {
+        for (stream, rng) in self.gameplay.iter_mut() {
+            *rng = StdRng::seed_from_u64(Self::mix(self.seed, stream.into_usize() as u64 + 1));
+        }
+        self.cosmetic = StdRng::seed_from_u64(Self::mix(self.seed, 0));
+    }
+
+    pub fn gameplay(&mut self, stream: RngStream) -> &mut StdRng {
+        &mut self.gameplay[stream]
+    }
+
+    pub fn cosmetic(&mut self) -> &mut StdRng {
+        &mut self.cosmetic
+    }
+
+    /// Seed for something derived from the main one
+    pub fn mix(seed: u64, value: u64) -> u64 {
+        seed ^ value.wrapping_mul(0x9e37_79b9_7f4a_7c15)
+    }
+}
+
+//
+
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameRng>();
+    }
+}
@@ -1,17 +1,40 @@
 use super::input::InputLock;
 use crate::common::*;
+use bevy::ecs::{
+    event::Events,
+    schedule::{IntoSystemDescriptor, ShouldRun},
+};
 
 /// Resource - gameplay time
 pub struct GameTime {
     now: Duration,
     delta: Duration,
+    real_now: Duration,
+    scale: f32,
+
+    /// Time not yet simulated
+    accumulator: Duration,
+    /// Running ticks for the current frame
+    looping: bool,
+    frame_delta: Duration,
 }
 
 impl GameTime {
+    /// Length of a single gameplay tick
+    pub const TICK: Duration = Duration::from_nanos(1_000_000_000 / 60);
+    /// Game slows down if frame takes longer than this
+    const MAX_TICKS_PER_FRAME: u32 = 5;
+
     pub fn now(&self) -> Duration {
         self.now
     }
 
+    /// Not affected by time scaling, stops only in main menu.
+    /// Use it instead of `Time` for anything which affects gameplay.
+    pub fn real_now(&self) -> Duration {
+        self.real_now
+    }
+
     pub fn delta(&self) -> Duration {
         self.delta
     }
@@ -21,6 +44,17 @@ impl GameTime {
         self.delta.as_secs_f32()
     }
 
+    /// Scaled duration of the current render frame.
+    /// Use it instead of `delta` outside of `GameStage`.
+    pub fn frame_delta_seconds(&self) -> f32 {
+        self.frame_delta.as_secs_f32()
+    }
+
+    /// How far render frame is between the previous tick and the next one, in [0; 1] range
+    pub fn tick_fraction(&self) -> f32 {
+        self.accumulator.as_secs_f32() / Self::TICK.as_secs_f32()
+    }
+
     pub fn reached(&self, time: Duration) -> bool {
         self.now >= time
     }
@@ -29,6 +63,10 @@ impl GameTime {
         self.now.checked_sub(since).unwrap_or_default()
     }
 
+    pub fn passed_real(&self, since: Duration) -> Duration {
+        self.real_now.checked_sub(since).unwrap_or_default()
+    }
+
     pub fn t_passed(&self, since: Duration, period: Duration) -> f32 {
         self.passed(since).as_secs_f32() / period.as_secs_f32()
     }
@@ -40,11 +78,58 @@ pub struct TimeMode {
     pub craft_menu: bool,
     pub player_alive: bool,
     pub overriden: Option<f32>,
+    /// Level editor is open, player can't be controlled
+    pub editor: bool,
+    pub editor_paused: bool,
 }
 
 impl TimeMode {
     pub fn stopped(&self) -> bool {
-        self.main_menu || self.craft_menu || !self.player_alive
+        self.main_menu || self.craft_menu || !self.player_alive || self.editor_paused
+    }
+}
+
+/// Stages of the gameplay schedule, which runs with fixed timestep after `CoreStage::Update`,
+/// zero or more times per frame.
+/// Everything which affects gameplay must be there, so it doesn't depend on framerate.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, StageLabel)]
+pub enum GameStage {
+    /// Schedule containing all other stages
+    Tick,
+    First,
+    PreUpdate,
+    Update,
+    PostUpdate,
+    Last,
+}
+
+/// Global transforms are propagated at the start of each tick, so entities spawned during
+/// the previous one have them even if there was no frame in between
+#[derive(SystemLabel)]
+pub struct TickPropagateSystemLabel;
+
+pub trait AppGameStage {
+    fn add_game_system<Params>(
+        &mut self, stage: GameStage, system: impl IntoSystemDescriptor<Params>,
+    ) -> &mut Self;
+
+    /// Event which is updated every tick instead of every frame,
+    /// so it isn't lost if there were no ticks during a frame
+    fn add_game_event<T: Send + Sync + 'static>(&mut self) -> &mut Self;
+}
+
+impl AppGameStage for App {
+    fn add_game_system<Params>(
+        &mut self, stage: GameStage, system: impl IntoSystemDescriptor<Params>,
+    ) -> &mut Self {
+        self.stage(GameStage::Tick, |schedule: &mut Schedule| {
+            schedule.add_system_to_stage(stage, system)
+        })
+    }
+
+    fn add_game_event<T: Send + Sync + 'static>(&mut self) -> &mut Self {
+        self.init_resource::<Events<T>>()
+            .add_game_system(GameStage::First, Events::<T>::update_system)
     }
 }
 
@@ -56,29 +141,73 @@ impl Plugin for TimePlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(GameTime {
             now: default(),
-            delta: Duration::from_secs_f32(1. / 60.),
+            delta: GameTime::TICK,
+            real_now: default(),
+            scale: 1.,
+            accumulator: default(),
+            looping: false,
+            frame_delta: default(),
         })
         .init_resource::<TimeMode>()
-        .add_system_to_stage(CoreStage::PreUpdate, advance_time);
+        .add_stage_after(
+            CoreStage::Update,
+            GameStage::Tick,
+            Schedule::default()
+                .with_run_criteria(fixed_tick)
+                .with_stage(GameStage::First, SystemStage::parallel())
+                .with_stage(GameStage::PreUpdate, SystemStage::parallel())
+                .with_stage(GameStage::Update, SystemStage::parallel())
+                .with_stage(GameStage::PostUpdate, SystemStage::parallel())
+                .with_stage(GameStage::Last, SystemStage::parallel()),
+        )
+        .add_game_system(GameStage::PreUpdate, advance_time)
+        .add_game_system(
+            GameStage::First,
+            bevy::transform::transform_propagate_system.label(TickPropagateSystemLabel),
+        );
+    }
+}
+
+fn fixed_tick(time: Res<Time>, mut game_time: ResMut<GameTime>) -> ShouldRun {
+    // first check in this frame
+    if !game_time.looping {
+        game_time.frame_delta = time.delta().mul_f32(game_time.scale);
+        game_time.accumulator = (game_time.accumulator + time.delta())
+            .min(GameTime::TICK * GameTime::MAX_TICKS_PER_FRAME);
+    }
+
+    if game_time.accumulator >= GameTime::TICK {
+        game_time.accumulator -= GameTime::TICK;
+        game_time.looping = true;
+        ShouldRun::YesAndCheckAgain
+    } else {
+        game_time.looping = false;
+        ShouldRun::No
     }
 }
 
 fn advance_time(
-    time: Res<Time>, mut game_time: ResMut<GameTime>, mut physics: ResMut<RapierConfiguration>,
-    mode: Res<TimeMode>, mut input_lock: ResMut<InputLock>,
+    mut game_time: ResMut<GameTime>, mut physics: ResMut<RapierConfiguration>, mode: Res<TimeMode>,
+    mut input_lock: ResMut<InputLock>,
 ) {
     // TODO: REMOVE THIS FROM HERE
     let scale = if mode.stopped() { 0. } else { mode.overriden.unwrap_or(1.) };
-    input_lock.active = mode.main_menu || mode.craft_menu;
+    input_lock.active = mode.main_menu || mode.craft_menu || mode.editor;
     input_lock.allow_craft = mode.craft_menu;
 
-    let delta = time.delta().mul_f32(scale);
+    if !mode.main_menu {
+        game_time.real_now += GameTime::TICK;
+    }
+
+    let delta = GameTime::TICK.mul_f32(scale);
+    game_time.scale = scale;
     game_time.delta = delta;
     game_time.now += delta;
 
-    physics.timestep_mode = TimestepMode::Interpolated {
-        dt: time.delta_seconds(),
-        time_scale: scale,
+    // zero timestep isn't supported
+    physics.physics_pipeline_active = scale > 0.;
+    physics.timestep_mode = TimestepMode::Fixed {
+        dt: delta.as_secs_f32(),
         substeps: 1,
     };
 }
@@ -12,6 +12,8 @@ mod mechanics;
 mod objects;
 mod present;
 mod settings;
+mod simulation;
+mod storage;
 mod utils;
 
 fn main() {
@@ -35,13 +37,37 @@ fn main() {
         );
     }
 
+    // hot-reload assets
+    #[cfg(feature = "debug_build")]
+    {
+        app.insert_resource(bevy::asset::AssetServerSettings {
+            watch_for_changes: true,
+            ..default()
+        });
+    }
+
     let mut args = std::env::args();
     args.next(); // skip program name
-    if let Some(arg) = args.next() {
+    while let Some(arg) = args.next() {
         match arg.as_str() {
             "play" => {
                 app.insert_resource(PlayNowHack(true));
             }
+            "seed" => {
+                let seed = args
+                    .next()
+                    .and_then(|seed| seed.parse().ok())
+                    .expect("Seed must be a number");
+                app.insert_resource(control::rng::GameRng::with_seed(seed));
+            }
+            "replay" => {
+                let path = args.next().expect("Replay file must be specified");
+                let data = control::replay::Replay::load_file(&path).expect("Invalid replay file");
+                let mut replay = control::replay::Replay::default();
+                replay.play(data);
+                app.insert_resource(replay)
+                    .insert_resource(PlayNowHack(true));
+            }
             _ => panic!("Invalid command-line argument"),
         }
     }
@@ -63,17 +89,24 @@ fn main() {
     .insert_resource(settings::Settings::load().unwrap_or_default())
     .add_plugins(DefaultPlugins)
     .add_plugin(EguiPlugin)
-    .add_plugin(bevy_prototype_lyon::plugin::ShapePlugin)
-    .add_plugin(RapierPhysicsPlugin::<()>::pixels_per_meter(1.))
-    .insert_resource({
-        let mut config = bevy_rapier2d::plugin::RapierConfiguration::default();
-        config.gravity = Vec2::ZERO; //-Vec2::Y * 10.;
-        config
-    })
-    .add_plugin(control::ControlPlugin)
-    .add_plugin(mechanics::MechanicsPlugin)
-    .add_plugin(objects::ObjectsPlugin)
-    .add_plugin(present::PresentationPlugin)
-    .add_plugin(assets::MyAssetsPlugin)
-    .run()
+    .add_plugin(bevy_prototype_lyon::plugin::ShapePlugin);
+    add_game_plugins(&mut app);
+    app.run()
+}
+
+/// Everything except the engine, also used by headless simulation
+fn add_game_plugins(app: &mut App) {
+    // physics stages are added by mechanics::physics
+    let physics = RapierPhysicsPlugin::<()>::pixels_per_meter(1.).with_default_system_setup(false);
+    app.add_plugin(physics)
+        .insert_resource({
+            let mut config = bevy_rapier2d::plugin::RapierConfiguration::default();
+            config.gravity = Vec2::ZERO; //-Vec2::Y * 10.;
+            config
+        })
+        .add_plugin(control::ControlPlugin)
+        .add_plugin(mechanics::MechanicsPlugin)
+        .add_plugin(objects::ObjectsPlugin)
+        .add_plugin(present::PresentationPlugin)
+        .add_plugin(assets::MyAssetsPlugin);
 }
@@ -1,7 +1,14 @@
+use super::{
+    behaviour::BehaviourSystemLabel,
+    movement::{KinematicCommand, KinematicController, MovementSystemLabel},
+    navigation::{PathSystemLabel, PathTo},
+};
 use crate::{
     common::*,
-    objects::{player::Player, weapon::Weapon},
+    control::rng::RngStream,
+    objects::{player::Player, weapon_def::WeaponList},
 };
+use serde::{Deserialize, Serialize};
 
 /// What should be targeted
 #[derive(Component)]
@@ -19,8 +26,13 @@ pub struct LosCheck {
 
     // state
     pub visible: bool,
+    /// Zero if target isn't moved by KinematicController
+    pub target_velocity: Vec2,
 }
 
+#[derive(SystemLabel)]
+pub struct LosSystemLabel;
+
 /// Rotate so it always tries to face the target.
 /// Requires LosCheck
 #[derive(Component, Default)]
@@ -28,35 +40,63 @@ pub struct FaceTarget {
     // config
     pub rotation_speed: f32,
     pub disabled: bool,
+    pub aim: AimMode,
+    /// From 0 to 1, how much of target movement is predicted and how small the random error is.
+    /// Used only when leading the target.
+    pub accuracy: f32,
 
     // state
     pub angle: f32,
+    error: f32,
+    next_error: Duration,
 }
 
+impl FaceTarget {
+    /// Angle at zero accuracy, 15 degrees
+    const MAX_ERROR: f32 = TAU / 24.;
+    const ERROR_PERIOD: Duration = Duration::from_millis(400);
+}
+
+#[derive(Clone, Default, Deserialize)]
+pub enum AimMode {
+    /// Face current position of the target
+    #[default]
+    Direct,
+    /// Face where target will be when projectile of that weapon reaches it
+    Lead { weapon: String },
+}
+
+/// Moves using KinematicController, steering around obstacles.
+/// If PathTo is present, it's used to reach the target when it's not visible.
 /// Requires LosCheck
 #[derive(Component, Default)]
-pub struct AttackPattern {
+pub struct MovementPattern {
     // config
-    pub stages: Vec<(Duration, AttackStage)>,
+    pub behaviour: MoveBehaviour,
 
     // state
-    pub stage: usize,
-    pub start: Option<Duration>,
+    pub waypoint: usize,
+    pub clockwise: bool,
 }
 
-impl AttackPattern {
-    pub fn stage(mut self, repeat: usize, duration: Duration, stage: AttackStage) -> Self {
-        for _ in 0..repeat {
-            self.stages.push((duration, stage.clone()))
-        }
-        self
-    }
+#[derive(Clone, Serialize, Deserialize)]
+pub enum MoveBehaviour {
+    /// Don't move at all
+    Stay,
+    /// Move straight to the target
+    Chase,
+    /// Circle around the target at that distance
+    Strafe { distance: f32 },
+    /// Approach or retreat to stay at that distance
+    KeepDistance { distance: f32 },
+    /// Visit points in order, ignoring the target
+    Patrol { points: Vec<Vec2> },
 }
 
-#[derive(Clone)]
-pub enum AttackStage {
-    Wait,
-    Shoot(Vec<Weapon>),
+impl Default for MoveBehaviour {
+    fn default() -> Self {
+        Self::Strafe { distance: 6. }
+    }
 }
 
 #[derive(Component)]
@@ -66,11 +106,10 @@ pub struct HeSpinsHeRotats {
 }
 
 impl HeSpinsHeRotats {
-    pub fn new(speed: f32) -> Self {
-        use rand::*;
+    pub fn new(speed: f32, rng: &mut impl rand::Rng) -> Self {
         Self {
             speed,
-            angle: thread_rng().gen_range(0. ..TAU),
+            angle: rng.gen_range(0. ..TAU),
         }
     }
 }
@@ -81,12 +120,27 @@ pub struct AiPlugin;
 
 impl Plugin for AiPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(new.exclusive_system().at_start())
-            .add_system(update_target)
-            .add_system(los_check.after(update_target))
-            .add_system(face_target.after(los_check))
-            .add_system(attack_pattern.after(face_target))
-            .add_system(spins_rotats);
+        app.add_game_system(GameStage::Update, new.exclusive_system().at_start())
+            .add_game_system(GameStage::Update, update_target)
+            .add_game_system(
+                GameStage::Update,
+                los_check.label(LosSystemLabel).after(update_target),
+            )
+            .add_game_system(
+                GameStage::Update,
+                face_target
+                    .after(LosSystemLabel)
+                    .after(BehaviourSystemLabel),
+            )
+            .add_game_system(
+                GameStage::Update,
+                movement_pattern
+                    .after(LosSystemLabel)
+                    .after(BehaviourSystemLabel)
+                    .before(PathSystemLabel)
+                    .before(MovementSystemLabel),
+            )
+            .add_game_system(GameStage::Update, spins_rotats);
     }
 }
 
@@ -118,14 +172,17 @@ fn update_target(
 
 fn los_check(
     mut entities: Query<(&GlobalTransform, &TargetState, &mut LosCheck)>,
-    targets: Query<&GlobalTransform>, phy: Res<RapierContext>,
+    targets: Query<(&GlobalTransform, Option<&KinematicController>)>, phy: Res<RapierContext>,
 ) {
     for (origin, target, mut los) in entities.iter_mut() {
-        los.visible = if let Some((target_pos, target)) =
+        los.visible = if let Some(((target_pos, controller), target)) =
             target.0.and_then(|e| targets.get(e).ok()).zip(target.0)
         {
             los.dir = target_pos.pos_2d() - origin.pos_2d();
             los.distance = los.dir.length();
+            los.target_velocity = controller
+                .map(|controller| controller.velocity)
+                .unwrap_or_default();
 
             if let Some((hit, _)) = phy.cast_ray(
                 origin.pos_2d(),
@@ -147,52 +204,184 @@ fn los_check(
 
 fn face_target(
     mut entities: Query<(&mut Transform, &mut FaceTarget, &LosCheck)>, time: Res<GameTime>,
+    weapons: Res<Assets<WeaponList>>, assets: Res<MyAssets>, mut rng: ResMut<GameRng>,
 ) {
     for (mut transform, mut target, los) in entities.iter_mut() {
         if target.disabled {
             continue;
         }
+        let speed = match &target.aim {
+            AimMode::Direct => None,
+            AimMode::Lead { weapon } => weapons
+                .get(&assets.weapons)
+                .and_then(|weapons| weapons.get(weapon))
+                .map(|def| def.speed),
+        };
+        let dir = match speed {
+            Some(speed) => {
+                if time.reached(target.next_error) {
+                    use rand::Rng;
+                    let max = FaceTarget::MAX_ERROR * (1. - target.accuracy);
+                    target.error = rng.gameplay(RngStream::Aim).gen_range(-max..=max);
+                    target.next_error = time.now() + FaceTarget::ERROR_PERIOD;
+                }
+                let velocity = los.target_velocity * target.accuracy;
+                let lead = intercept_time(los.dir, velocity, speed)
+                    .map(|time| velocity * time)
+                    .unwrap_or_default();
+                (los.dir + lead).rotated(target.error)
+            }
+            None => los.dir,
+        };
         let speed = target.rotation_speed * time.delta_seconds();
-        let delta = angle_delta(los.dir.angle(), target.angle).clamp(-speed, speed);
+        let delta = angle_delta(dir.angle(), target.angle).clamp(-speed, speed);
         target.angle += delta;
         transform.set_angle_2d(target.angle);
     }
 }
 
-fn attack_pattern(
-    mut entities: Query<(Entity, &mut AttackPattern)>, time: Res<GameTime>,
-    mut weapon_commands: CmdWriter<Weapon>,
+/// When projectile with that speed reaches target moving with constant velocity
+fn intercept_time(dir: Vec2, velocity: Vec2, speed: f32) -> Option<f32> {
+    let a = velocity.length_squared() - speed * speed;
+    let b = 2. * dir.dot(velocity);
+    let c = dir.length_squared();
+    if a.abs() < 0.001 {
+        return (b < 0.).then(|| -c / b);
+    }
+    let discriminant = b * b - 4. * a * c;
+    if discriminant < 0. {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    [(-b - root) / (2. * a), (-b + root) / (2. * a)]
+        .into_iter()
+        .filter(|time| *time > 0.)
+        .reduce(f32::min)
+}
+
+fn movement_pattern(
+    mut entities: Query<(
+        Entity,
+        &GlobalTransform,
+        &KinematicController,
+        &LosCheck,
+        &TargetState,
+        &mut MovementPattern,
+        Option<&mut PathTo>,
+    )>,
+    phy: Res<RapierContext>, mut kinematic: CmdWriter<KinematicCommand>,
 ) {
-    for (entity, mut pattern) in entities.iter_mut() {
-        if pattern.stages.is_empty() {
-            continue;
-        }
+    // ignore target when closer than that
+    let min_distance = 1.5;
+    let waypoint_radius = 0.5;
 
-        let passed = time.passed(*pattern.start.get_or_insert(time.now()));
-        let duration = pattern
-            .stages
-            .get(pattern.stage)
-            .map(|v| v.0)
-            .unwrap_or_default();
-        if passed >= duration {
-            pattern.stage += 1;
-            pattern.start = None;
-            if pattern.stage >= pattern.stages.len() {
-                pattern.stage = 0;
-            }
+    for (entity, pos, controller, los, target, mut pattern, mut path) in entities.iter_mut() {
+        let pos = pos.pos_2d();
+        let to_target = los.dir.try_normalize().unwrap_or_default();
+        let has_target = target.0.is_some() && los.distance > min_distance;
 
-            match &pattern.stages.get(pattern.stage).unwrap().1 {
-                AttackStage::Wait => (),
-                AttackStage::Shoot(weapon) => {
-                    for weapon in weapon {
-                        weapon_commands.send((entity, *weapon))
+        // walk around walls instead of steering
+        let path_target = match &pattern.behaviour {
+            MoveBehaviour::Stay => None,
+            MoveBehaviour::Patrol { points } => points.get(pattern.waypoint).copied(),
+            _ if has_target && !los.visible => Some(pos + los.dir),
+            _ => None,
+        };
+        if let Some(path) = path.as_mut() {
+            path.target = path_target;
+        }
+
+        let dir = match &pattern.behaviour {
+            MoveBehaviour::Stay => continue,
+            MoveBehaviour::Patrol { points } => match points.get(pattern.waypoint) {
+                Some(point) => {
+                    if point.distance(pos) < waypoint_radius {
+                        pattern.waypoint = (pattern.waypoint + 1) % points.len();
+                    }
+                    if path.is_some() {
+                        continue;
                     }
+                    *point - pos
                 }
+                None => continue,
+            },
+            _ if !has_target => continue,
+            // get closer to see the target
+            _ if !los.visible => match path {
+                Some(_) => continue,
+                None => to_target,
+            },
+
+            MoveBehaviour::Chase => to_target,
+            MoveBehaviour::Strafe { distance } => {
+                let radial = ((los.distance - distance) / distance).clamp(-1., 1.);
+                let tangent = if pattern.clockwise {
+                    to_target.clockwise90()
+                } else {
+                    -to_target.clockwise90()
+                };
+                tangent + to_target * radial
             }
+            MoveBehaviour::KeepDistance { distance } => {
+                let radial = (los.distance - distance) / distance;
+                if radial.abs() < 0.1 {
+                    continue;
+                }
+                to_target * radial.clamp(-1., 1.)
+            }
+        };
+        let dir = match dir.try_normalize() {
+            Some(dir) => dir,
+            None => continue,
+        };
+
+        match steer(
+            &phy,
+            entity,
+            target.0,
+            pos,
+            dir,
+            controller.radius,
+            pattern.clockwise,
+        ) {
+            Some(dir) => kinematic.send((entity, KinematicCommand::Move { dir })),
+            // try other way next time
+            None => pattern.clockwise = !pattern.clockwise,
         }
     }
 }
 
+/// Direction closest to the desired one which isn't blocked by obstacles
+fn steer(
+    phy: &RapierContext, entity: Entity, target: Option<Entity>, pos: Vec2, dir: Vec2, radius: f32,
+    clockwise: bool,
+) -> Option<Vec2> {
+    let lookahead = radius + 1.;
+    let filter = QueryFilter::new()
+        .exclude_rigid_body(entity)
+        .groups(PhysicsType::MovementController.into());
+    let side = if clockwise { -1. } else { 1. };
+
+    [0., 30., -30., 60., -60., 90., -90., 120., -120.]
+        .into_iter()
+        .map(|angle: f32| dir.rotated(angle.to_radians() * side))
+        .find(|dir| {
+            // sides of the body must be free too
+            [0., radius * 0.8, -radius * 0.8].into_iter().all(|offset| {
+                match phy.cast_ray(
+                    pos + dir.clockwise90() * offset,
+                    *dir,
+                    lookahead,
+                    true,
+                    filter,
+                ) {
+                    Some((hit, _)) => Some(hit) == target,
+                    None => true,
+                }
+            })
+        })
+}
+
 fn spins_rotats(mut entities: Query<(&mut Transform, &mut HeSpinsHeRotats)>, time: Res<GameTime>) {
     for (mut transform, mut spins) in entities.iter_mut() {
         spins.angle += spins.speed * time.delta_seconds();
@@ -0,0 +1,486 @@
+use super::{
+    ai::{AimMode, FaceTarget, LosCheck, LosSystemLabel, MoveBehaviour, MovementPattern},
+    health::Health,
+    movement::KinematicController,
+};
+use crate::{
+    assets::{AppRonAsset, RonAsset},
+    common::*,
+    objects::{
+        player::Player,
+        spawn::{SummonEvent, TurretType},
+        waves::Arena,
+        weapon::Weapon,
+        weapon_def::WeaponList,
+    },
+    present::effect::ChargingAttack,
+};
+use bevy::reflect::TypeUuid;
+use serde::Deserialize;
+
+/// Asset - enemy behaviour trees, referred by `Behaviour::id`
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "8f1e2c7a-3b4d-4e59-a6c1-0d9b7e5f2a14"]
+pub struct BehaviourList {
+    pub behaviours: HashMap<String, AiNode>,
+}
+
+impl RonAsset for BehaviourList {
+    const EXTENSIONS: &'static [&'static str] = &["behaviours.ron"];
+}
+
+/// Each tick node either finishes with success or failure, or keeps running.
+/// Running node is resumed on the next tick.
+#[derive(Clone, Deserialize)]
+pub enum AiNode {
+    /// Runs children in order until one fails
+    Sequence(Vec<AiNode>),
+    /// Runs children in order until one succeeds
+    Select(Vec<AiNode>),
+    /// Restarts child when it finishes, never finishes itself
+    Loop(Box<AiNode>),
+    /// Runs child that many times, stops if it fails
+    Repeat(usize, Box<AiNode>),
+    /// Runs child while condition holds, aborts it and fails otherwise
+    While(AiCondition, Box<AiNode>),
+
+    Check(AiCondition),
+    /// Seconds
+    Wait(f32),
+    /// Weapon IDs, all fired at once
+    Shoot(Vec<String>),
+    Aim(AimMode),
+    /// Patrol points are normalized
+    Move(MoveBehaviour),
+    /// Shows charging effect and waits until it ends
+    Charge {
+        radius: f32,
+        seconds: f32,
+        color: Color,
+    },
+    /// Spawns turrets evenly around at that distance
+    Summon {
+        ty: TurretType,
+        count: usize,
+        radius: f32,
+    },
+}
+
+#[derive(Clone, Deserialize)]
+pub enum AiCondition {
+    /// Line of sight to the target
+    Visible,
+    CloserThan(f32),
+    FartherThan(f32),
+    /// Fraction of max health
+    HealthBelow(f32),
+    PlayerDashing,
+    Not(Box<AiCondition>),
+}
+
+/// Runs behaviour tree from `BehaviourList`.
+/// Conditions use LosCheck and Health if present.
+#[derive(Component)]
+pub struct Behaviour {
+    // config
+    pub id: String,
+
+    // state
+    memory: HashMap<usize, NodeMemory>,
+    /// Running nodes, from root to leaf
+    trace: Vec<String>,
+}
+
+impl Behaviour {
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            memory: default(),
+            trace: vec![],
+        }
+    }
+}
+
+#[derive(SystemLabel)]
+pub struct BehaviourSystemLabel;
+
+/// Resource - shows state of all enemies
+#[derive(Default)]
+pub struct AiDebug {
+    pub enabled: bool,
+}
+
+//
+
+pub struct BehaviourPlugin;
+
+impl Plugin for BehaviourPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_ron_asset::<BehaviourList>()
+            .init_resource::<AiDebug>()
+            .add_game_system(
+                GameStage::Update,
+                run_behaviour
+                    .label(BehaviourSystemLabel)
+                    .after(LosSystemLabel),
+            )
+            .add_system(check_weapons);
+        if !app.is_headless() {
+            app.add_system(debug_overlay);
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Success,
+    Failure,
+    Running,
+}
+
+/// State of a running node
+#[derive(Clone, Copy, Default)]
+struct NodeMemory {
+    /// Current child or repetition
+    index: usize,
+    start: Option<Duration>,
+}
+
+enum Action {
+    Shoot(String),
+    Aim(AimMode),
+    Move(MoveBehaviour),
+    Charge(ChargingAttack),
+    Summon {
+        ty: TurretType,
+        count: usize,
+        radius: f32,
+    },
+}
+
+struct Context {
+    now: Duration,
+    visible: bool,
+    distance: f32,
+    health: f32,
+    player_dashing: bool,
+
+    actions: Vec<Action>,
+    trace: Option<Vec<String>>,
+}
+
+impl AiCondition {
+    fn check(&self, ctx: &Context) -> bool {
+        match self {
+            AiCondition::Visible => ctx.visible,
+            AiCondition::CloserThan(distance) => ctx.distance < *distance,
+            AiCondition::FartherThan(distance) => ctx.distance > *distance,
+            AiCondition::HealthBelow(fraction) => ctx.health < *fraction,
+            AiCondition::PlayerDashing => ctx.player_dashing,
+            AiCondition::Not(condition) => !condition.check(ctx),
+        }
+    }
+}
+
+impl AiNode {
+    /// Nodes are identified by index in depth-first order, so subtree is a continuous range
+    fn size(&self) -> usize {
+        1 + match self {
+            AiNode::Sequence(nodes) | AiNode::Select(nodes) => nodes.iter().map(Self::size).sum(),
+            AiNode::Loop(node) | AiNode::Repeat(_, node) | AiNode::While(_, node) => node.size(),
+            _ => 0,
+        }
+    }
+
+    /// Calls function for each weapon ID used in the subtree
+    fn weapons(&self, f: &mut impl FnMut(&str)) {
+        match self {
+            AiNode::Sequence(nodes) | AiNode::Select(nodes) => {
+                nodes.iter().for_each(|node| node.weapons(f))
+            }
+            AiNode::Loop(node) | AiNode::Repeat(_, node) | AiNode::While(_, node) => {
+                node.weapons(f)
+            }
+            AiNode::Shoot(ids) => ids.iter().for_each(|id| f(id)),
+            AiNode::Aim(AimMode::Lead { weapon }) => f(weapon),
+            _ => (),
+        }
+    }
+
+    fn tick(
+        &self, id: usize, memory: &mut HashMap<usize, NodeMemory>, ctx: &mut Context,
+    ) -> Status {
+        let mut state = memory.get(&id).copied().unwrap_or_default();
+        let status = match self {
+            AiNode::Sequence(nodes) | AiNode::Select(nodes) => {
+                // status which moves to the next child
+                let next = match self {
+                    AiNode::Sequence(_) => Status::Success,
+                    _ => Status::Failure,
+                };
+                loop {
+                    let node = match nodes.get(state.index) {
+                        Some(node) => node,
+                        None => break next,
+                    };
+                    let child = id + 1 + nodes[..state.index].iter().map(Self::size).sum::<usize>();
+                    let status = node.tick(child, memory, ctx);
+                    if status != next {
+                        break status;
+                    }
+                    state.index += 1;
+                }
+            }
+            AiNode::Loop(node) => {
+                node.tick(id + 1, memory, ctx);
+                Status::Running
+            }
+            AiNode::Repeat(count, node) => loop {
+                if state.index >= *count {
+                    break Status::Success;
+                }
+                match node.tick(id + 1, memory, ctx) {
+                    Status::Success => state.index += 1,
+                    status => break status,
+                }
+            },
+            AiNode::While(condition, node) => {
+                if condition.check(ctx) {
+                    node.tick(id + 1, memory, ctx)
+                } else {
+                    Status::Failure
+                }
+            }
+
+            AiNode::Check(condition) => match condition.check(ctx) {
+                true => Status::Success,
+                false => Status::Failure,
+            },
+            AiNode::Wait(seconds) => {
+                let start = *state.start.get_or_insert(ctx.now);
+                match ctx.now.saturating_sub(start) >= Duration::from_secs_f32(*seconds) {
+                    true => Status::Success,
+                    false => Status::Running,
+                }
+            }
+            AiNode::Shoot(weapons) => {
+                for id in weapons {
+                    ctx.actions.push(Action::Shoot(id.clone()))
+                }
+                Status::Success
+            }
+            AiNode::Aim(mode) => {
+                ctx.actions.push(Action::Aim(mode.clone()));
+                Status::Success
+            }
+            AiNode::Move(behaviour) => {
+                ctx.actions.push(Action::Move(behaviour.clone()));
+                Status::Success
+            }
+            AiNode::Charge {
+                radius,
+                seconds,
+                color,
+            } => {
+                let duration = Duration::from_secs_f32(*seconds);
+                let start = *state.start.get_or_insert_with(|| {
+                    ctx.actions.push(Action::Charge(ChargingAttack {
+                        radius: *radius,
+                        duration,
+                        color: *color,
+                    }));
+                    ctx.now
+                });
+                match ctx.now.saturating_sub(start) >= duration {
+                    true => Status::Success,
+                    false => Status::Running,
+                }
+            }
+            AiNode::Summon { ty, count, radius } => {
+                ctx.actions.push(Action::Summon {
+                    ty: *ty,
+                    count: *count,
+                    radius: *radius,
+                });
+                Status::Success
+            }
+        };
+
+        if status == Status::Running {
+            memory.insert(id, state);
+            if let Some(trace) = ctx.trace.as_mut() {
+                trace.push(self.name(&state))
+            }
+        } else {
+            // also clears children which were aborted
+            let subtree = id..id + self.size();
+            memory.retain(|id, _| !subtree.contains(id));
+        }
+        status
+    }
+
+    /// For debug overlay
+    fn name(&self, state: &NodeMemory) -> String {
+        match self {
+            AiNode::Sequence(nodes) => format!("Sequence {}/{}", state.index + 1, nodes.len()),
+            AiNode::Select(nodes) => format!("Select {}/{}", state.index + 1, nodes.len()),
+            AiNode::Loop(_) => "Loop".to_string(),
+            AiNode::Repeat(count, _) => format!("Repeat {}/{}", state.index + 1, count),
+            AiNode::While(..) => "While".to_string(),
+            AiNode::Wait(seconds) => format!("Wait {:.1}s", seconds),
+            AiNode::Charge { seconds, .. } => format!("Charge {:.1}s", seconds),
+            _ => "???".to_string(),
+        }
+    }
+}
+
+fn run_behaviour(
+    mut commands: Commands,
+    mut entities: Query<(
+        Entity,
+        &GlobalTransform,
+        &mut Behaviour,
+        Option<&LosCheck>,
+        Option<&Health>,
+        Option<&mut FaceTarget>,
+        Option<&mut MovementPattern>,
+    )>,
+    player: Query<&KinematicController, With<Player>>, lists: Res<Assets<BehaviourList>>,
+    assets: Res<MyAssets>, time: Res<GameTime>, debug: Res<AiDebug>,
+    mut weapon_cmd: CmdWriter<Weapon>, mut summon: EventWriter<SummonEvent>,
+) {
+    let list = match lists.get(&assets.behaviours) {
+        Some(list) => list,
+        None => return,
+    };
+    let player_dashing = player
+        .get_single()
+        .map(|controller| controller.dash.is_some())
+        .unwrap_or(false);
+
+    for (entity, pos, mut behaviour, los, health, mut face, mut movement) in entities.iter_mut() {
+        let behaviour = &mut *behaviour;
+        let tree = match list.behaviours.get(&behaviour.id) {
+            Some(tree) => tree,
+            None => {
+                log::error!("No behaviour \"{}\"", behaviour.id);
+                continue;
+            }
+        };
+
+        let mut ctx = Context {
+            now: time.now(),
+            visible: los.map(|los| los.visible).unwrap_or(false),
+            distance: los.map(|los| los.distance).unwrap_or(f32::INFINITY),
+            health: health.map(|health| health.value / health.max).unwrap_or(1.),
+            player_dashing,
+            actions: vec![],
+            trace: debug.enabled.then(Vec::new),
+        };
+        tree.tick(0, &mut behaviour.memory, &mut ctx);
+        if let Some(mut trace) = ctx.trace {
+            trace.reverse();
+            behaviour.trace = trace;
+        }
+
+        for action in ctx.actions {
+            match action {
+                Action::Shoot(id) => weapon_cmd.send((entity, Weapon::Projectile { id })),
+                Action::Aim(mode) => {
+                    if let Some(face) = face.as_mut() {
+                        face.aim = mode
+                    }
+                }
+                Action::Move(behaviour) => {
+                    if let Some(movement) = movement.as_mut() {
+                        movement.behaviour = match behaviour {
+                            MoveBehaviour::Patrol { points } => MoveBehaviour::Patrol {
+                                points: points
+                                    .into_iter()
+                                    .map(|pos| Arena::default().to_world(pos))
+                                    .collect(),
+                            },
+                            behaviour => behaviour,
+                        };
+                        movement.waypoint = 0;
+                    }
+                }
+                Action::Charge(charge) => {
+                    commands.entity(entity).insert(charge);
+                }
+                Action::Summon { ty, count, radius } => summon.send(SummonEvent {
+                    origin: pos.pos_2d(),
+                    ty,
+                    count,
+                    radius,
+                }),
+            }
+        }
+    }
+}
+
+/// Reports unknown weapons once, when behaviours or weapons are loaded
+fn check_weapons(
+    mut behaviour_events: EventReader<AssetEvent<BehaviourList>>,
+    mut weapon_events: EventReader<AssetEvent<WeaponList>>, behaviours: Res<Assets<BehaviourList>>,
+    weapons: Res<Assets<WeaponList>>, assets: Res<MyAssets>, mut pending: Local<bool>,
+) {
+    // both events must be consumed
+    if behaviour_events.iter().count() + weapon_events.iter().count() != 0 {
+        *pending = true
+    }
+    if !*pending {
+        return;
+    }
+    let (behaviours, weapons) = match (
+        behaviours.get(&assets.behaviours),
+        weapons.get(&assets.weapons),
+    ) {
+        (Some(behaviours), Some(weapons)) => (behaviours, weapons),
+        _ => return,
+    };
+    *pending = false;
+
+    for (id, node) in behaviours.behaviours.iter() {
+        node.weapons(&mut |weapon| {
+            if weapons.get(weapon).is_none() {
+                log::error!("No weapon \"{}\" (behaviour: \"{}\")", weapon, id)
+            }
+        })
+    }
+}
+
+fn debug_overlay(
+    keys: Res<Input<KeyCode>>, mut debug: ResMut<AiDebug>, mut ctx: ResMut<EguiContext>,
+    entities: Query<(Entity, &Behaviour, Option<&LosCheck>, Option<&Health>)>,
+) {
+    if keys.just_pressed(KeyCode::F3) {
+        debug.enabled = !debug.enabled
+    }
+    if !debug.enabled {
+        return;
+    }
+
+    egui::Window::new("AI")
+        .anchor(egui::Align2::LEFT_TOP, egui::vec2(0., 0.))
+        .resizable(false)
+        .show(ctx.ctx_mut(), |ui| {
+            for (entity, behaviour, los, health) in entities.iter() {
+                ui.label(format!(
+                    "{:?} \"{}\": health {}, {}",
+                    entity,
+                    behaviour.id,
+                    health
+                        .map(|health| format!("{:.1}/{:.1}", health.value, health.max))
+                        .unwrap_or_else(|| "-".to_string()),
+                    los.map(|los| format!(
+                        "{} at {:.1}",
+                        if los.visible { "visible" } else { "hidden" },
+                        los.distance
+                    ))
+                    .unwrap_or_else(|| "no target".to_string()),
+                ));
+                ui.label(format!("    {}", behaviour.trace.join(" > ")));
+            }
+        });
+}
@@ -4,18 +4,23 @@ use crate::{
     objects::stats::Stats,
     present::effect::{Explosion, ExplosionPower, RayEffect},
 };
+use serde::Deserialize;
 
-#[derive(Component, Clone, Copy)]
+#[derive(Component, Clone, Copy, Deserialize)]
 pub enum Team {
     Player,
     Enemy,
     YEEEEEEE,
+    /// Hazards: damaged by and damage both teams, but not each other
+    Neutral,
 }
 
 impl Team {
     pub fn is_same(&self, rhs: Team) -> bool {
         match (self, rhs) {
-            (Team::Player, Team::Player) | (Team::Enemy, Team::Enemy) => true,
+            (Team::Player, Team::Player)
+            | (Team::Enemy, Team::Enemy)
+            | (Team::Neutral, Team::Neutral) => true,
             (Team::YEEEEEEE, _) | (_, Team::YEEEEEEE) => false,
             _ => false,
         }
@@ -41,6 +46,8 @@ pub struct DamageRay {
     pub spawn_effect: Option<RayEffect>, // length will be set on hit
     pub explosion_effect: Option<Explosion>, // show explosion where it hits
     pub ignore_obstacles: bool,
+    /// Number of targets pierced without losing damage
+    pub pierce: usize,
 }
 
 #[derive(Component, Clone, Copy)]
@@ -60,17 +67,65 @@ pub struct BigProjectile;
 #[derive(Component)]
 pub struct BonkToTeam(pub Team);
 
+/// Entity which fired the projectile, or took it over by bonking, punching or repelling it.
+/// Force fields with the same owner let it pass regardless of the team.
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub struct Owner(pub Entity);
+
+/// Pushes nearby projectiles away and converts them to the same team.
+/// Requires Team, works once after creation.
+#[derive(Component)]
+pub struct Repel {
+    pub radius: f32,
+    /// Added to the speed of the projectile
+    pub speed: f32,
+    /// Explosive projectiles will explode as if hit by powerful damage
+    pub activate: bool,
+}
+
+/// Deflects nearby big projectiles towards the target, and converts them to the same team.
+/// Requires Team. Works each tick until the time is reached, but only once per projectile.
+#[derive(Component)]
+pub struct Punch {
+    pub radius: f32,
+    pub target: Vec2,
+    pub until: Duration,
+    pub punched: Vec<Entity>,
+}
+
+impl Punch {
+    /// Applied to damage and speed of the projectile
+    const MULTIPLIER: f32 = 2.;
+}
+
+/// Absorbs projectiles and weakens rays of other teams. Requires Health, which is recharged.
+/// Projectiles of its `Owner` pass through too.
+#[derive(Component)]
+pub struct ForceField {
+    /// Part of ray damage which is absorbed, in [0; 1] range
+    pub ray_absorb: f32,
+    /// Health per second
+    pub recharge: f32,
+}
+
+/// Stops rays of other teams, unless their damage is powerful. Requires Team.
+#[derive(Component)]
+pub struct RayShield;
+
 //
 
 pub struct DamagePlugin;
 
 impl Plugin for DamagePlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(damage_on_contact)
-            .add_system(die_on_contact)
-            .add_system_to_stage(CoreStage::PostUpdate, damage_ray)
-            .add_system_to_stage(CoreStage::PostUpdate, explode_on_death.after(damage_ray))
-            .add_system_to_stage(CoreStage::Last, bonk_to_same_team);
+        app.add_game_system(GameStage::Update, damage_on_contact)
+            .add_game_system(GameStage::Update, die_on_contact)
+            .add_game_system(GameStage::PostUpdate, damage_ray)
+            .add_game_system(GameStage::PostUpdate, explode_on_death.after(damage_ray))
+            .add_game_system(GameStage::Last, bonk_to_same_team)
+            .add_game_system(GameStage::Update, recharge_force_field)
+            .add_game_system(GameStage::Update, repel)
+            .add_game_system(GameStage::Update, punch);
     }
 }
 
@@ -80,10 +135,16 @@ fn damage_on_contact(
         With<DamageOnContact>,
     >,
     targets: Query<&GlobalTransform, With<Health>>, mut damage_cmd: CmdWriter<DamageEvent>,
-    phy: Res<RapierContext>,
+    phy: Res<RapierContext>, owners: Query<&Owner>, fields: Query<&Team, With<ForceField>>,
 ) {
     for (source, contacts, origin, damage, team) in entities.iter() {
+        let owner = owners.get(source).ok();
         for entity in contacts.current.iter().copied() {
+            if let Ok(field) = fields.get(entity) {
+                if passes_field(*team, owner, (field, owners.get(entity).ok())) {
+                    continue;
+                }
+            }
             if let Ok(pos) = targets.get(entity) {
                 let origin = origin.pos_2d();
                 let dir = pos.pos_2d() - origin;
@@ -113,21 +174,47 @@ fn damage_on_contact(
     }
 }
 
+/// Projectile isn't blocked by the force field or the ray shield
+fn passes_field(team: Team, owner: Option<&Owner>, field: (&Team, Option<&Owner>)) -> bool {
+    team.is_same(*field.0) || (owner.is_some() && owner == field.1)
+}
+
 fn die_on_contact(
-    entities: Query<(Entity, &CollectContacts, Option<&BigProjectile>), With<DieOnContact>>,
+    entities: Query<
+        (
+            Entity,
+            &CollectContacts,
+            Option<&BigProjectile>,
+            &Team,
+            Option<&Owner>,
+        ),
+        With<DieOnContact>,
+    >,
     mut death: CmdWriter<DeathEvent>, projectiles: Query<(), With<SmallProjectile>>,
     invincible: Query<&Health>,
+    fields: Query<(&Team, Option<&Owner>), Or<(With<ForceField>, With<RayShield>)>>,
 ) {
-    for (entity, contacts, big) in entities.iter() {
+    for (entity, contacts, big, team, owner) in entities.iter() {
+        // force fields are sensors, so that's the only thing which makes them block projectiles
+        let contacts: Vec<_> = contacts
+            .current
+            .iter()
+            .copied()
+            .filter(|e| {
+                !fields
+                    .get(*e)
+                    .map(|field| passes_field(*team, owner, field))
+                    .unwrap_or(false)
+            })
+            .collect();
         if match big.is_some() {
             true => {
-                contacts.current.iter().any(|e| !projectiles.contains(*e))
+                contacts.iter().any(|e| !projectiles.contains(*e))
                     && contacts
-                        .current
                         .iter()
                         .any(|e| !invincible.get(*e).map(|hp| hp.invincible).unwrap_or(false))
             }
-            false => !contacts.current.is_empty(),
+            false => !contacts.is_empty(),
         } {
             death.send((entity, default()))
         }
@@ -141,13 +228,16 @@ fn damage_ray(
         &Health,
         Option<&SmallProjectile>,
         Option<&mut ExplodeOnDeath>,
+        Option<&ForceField>,
     )>,
-    mut damage_cmd: CmdWriter<DamageEvent>, phy: Res<RapierContext>, mut commands: Commands,
-    mut explode: EventWriter<Explosion>, mut stats: ResMut<Stats>,
+    shields: Query<&Team, With<RayShield>>, mut damage_cmd: CmdWriter<DamageEvent>,
+    phy: Res<RapierContext>, mut commands: Commands, mut explode: EventWriter<Explosion>,
+    mut stats: ResMut<Stats>,
 ) {
     let huge_distance = 1000.;
 
     for (source, pos, ray, mut damage, team) in rays.iter_mut() {
+        let mut pierce = ray.pierce;
         let dir = Vec2::Y.rotated(pos.angle_2d());
 
         let mut best_targets = vec![];
@@ -161,6 +251,11 @@ fn damage_ray(
                 let same_team = targets
                     .get(entity)
                     .map(|other_team| team.is_same(*other_team.0))
+                    .or_else(|_| {
+                        shields
+                            .get(entity)
+                            .map(|other_team| team.is_same(*other_team))
+                    })
                     .unwrap_or(false);
                 if !same_team && targets.get(entity).map(|v| !v.1.invincible).unwrap_or(true) {
                     best_targets.push((entity, intersect.toi, intersect.point))
@@ -177,6 +272,12 @@ fn damage_ray(
                 explosion.origin = point;
                 explode.send(explosion)
             }
+            if shields.contains(entity) {
+                if damage.powerful {
+                    continue;
+                }
+                break;
+            }
 
             damage_cmd.send((
                 entity,
@@ -188,7 +289,7 @@ fn damage_ray(
                 },
             ));
 
-            if let Ok((_, health, projectile, mut explode)) = targets.get_mut(entity) {
+            if let Ok((_, health, projectile, mut explode, field)) = targets.get_mut(entity) {
                 if team.is_player() {
                     if let Some(explode) = explode.as_mut().filter(|_| damage.powerful) {
                         stats.player.points += 50;
@@ -208,7 +309,11 @@ fn damage_ray(
                         stats.player.points += 1
                     }
                 }
-                if health.value < damage.value {
+                if let Some(field) = field {
+                    damage.value *= 1. - field.ray_absorb;
+                } else if health.value < damage.value && pierce > 0 {
+                    pierce -= 1;
+                } else if health.value < damage.value {
                     let new_damage =
                         damage.value - health.value - if health.max > 5. { 2. } else { 1. };
                     if new_damage < 0. {
@@ -281,14 +386,133 @@ fn explode_on_death(
 }
 
 fn bonk_to_same_team(
-    bonker: Query<(&CollectContacts, &BonkToTeam)>,
+    mut commands: Commands, bonker: Query<(Entity, &CollectContacts, &BonkToTeam)>,
     mut projectiles: Query<&mut Team, With<BigProjectile>>,
 ) {
-    for (contacts, bonk) in bonker.iter() {
+    for (bonker, contacts, bonk) in bonker.iter() {
         for entity in &contacts.current {
             if let Ok(mut team) = projectiles.get_mut(*entity) {
-                *team = bonk.0
+                *team = bonk.0;
+                commands.entity(*entity).insert(Owner(bonker));
+            }
+        }
+    }
+}
+
+fn recharge_force_field(mut fields: Query<(&ForceField, &mut Health)>, time: Res<GameTime>) {
+    for (field, mut health) in fields.iter_mut() {
+        health.value = (health.value + field.recharge * time.delta_seconds()).min(health.max);
+    }
+}
+
+fn repel(
+    mut commands: Commands,
+    repellers: Query<(&Transform, &Repel, &Team, Option<&Owner>), Added<Repel>>,
+    mut projectiles: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut Velocity,
+            &mut Team,
+            Option<&mut ExplodeOnDeath>,
+        ),
+        (
+            Or<(With<SmallProjectile>, With<BigProjectile>)>,
+            Without<Repel>,
+        ),
+    >,
+) {
+    for (origin, repel, team, owner) in repellers.iter() {
+        let origin = origin.pos_2d();
+        for (entity, mut transform, mut velocity, mut projectile_team, explode) in
+            projectiles.iter_mut()
+        {
+            let delta = transform.pos_2d() - origin;
+            if delta.length() > repel.radius {
+                continue;
+            }
+            let dir = delta.try_normalize().unwrap_or(Vec2::Y);
+
+            // cancel movement towards the origin, as if it bounced
+            let incoming = velocity.linvel.dot(-dir).max(0.);
+            velocity.linvel += dir * (incoming * 2. + repel.speed);
+            transform.set_angle_2d(velocity.linvel.angle());
+
+            *projectile_team = *team;
+            if let Some(owner) = owner {
+                commands.entity(entity).insert(*owner);
             }
+            if let Some(mut explode) = explode.filter(|_| repel.activate) {
+                explode.activated = true
+            }
+        }
+    }
+}
+
+fn punch(
+    mut commands: Commands, mut punchers: Query<(Entity, &GlobalTransform, &mut Punch, &Team)>,
+    mut projectiles: Query<
+        (
+            &mut Transform,
+            &mut Velocity,
+            &mut Team,
+            &mut Damage,
+            Option<&mut ExplodeOnDeath>,
+        ),
+        (With<BigProjectile>, Without<Punch>),
+    >,
+    phy: Res<RapierContext>, time: Res<GameTime>, mut explode: EventWriter<Explosion>,
+) {
+    for (entity, origin, mut punch, team) in punchers.iter_mut() {
+        let origin = origin.pos_2d();
+
+        let mut hit = vec![];
+        phy.intersections_with_shape(
+            origin,
+            0.,
+            &Collider::ball(punch.radius),
+            QueryFilter::new().groups(PhysicsType::Hitscan.into()),
+            |entity| {
+                if projectiles.contains(entity) && !punch.punched.contains(&entity) {
+                    hit.push(entity)
+                }
+                true
+            },
+        );
+
+        for projectile in hit {
+            if let Ok((mut transform, mut velocity, mut projectile_team, mut damage, explode_on)) =
+                projectiles.get_mut(projectile)
+            {
+                let pos = transform.pos_2d();
+                let dir = (punch.target - pos)
+                    .try_normalize()
+                    .or_else(|| velocity.linvel.try_normalize())
+                    .unwrap_or(Vec2::Y);
+                velocity.linvel = dir * velocity.linvel.length() * Punch::MULTIPLIER;
+                transform.set_angle_2d(dir.angle());
+
+                *projectile_team = *team;
+                commands.entity(projectile).insert(Owner(entity));
+                damage.value *= Punch::MULTIPLIER;
+                if let Some(mut explode_on) = explode_on {
+                    explode_on.damage *= Punch::MULTIPLIER
+                }
+
+                explode.send(Explosion {
+                    origin: pos,
+                    color0: Color::WHITE,
+                    color1: Color::CYAN,
+                    time: Duration::from_millis(150),
+                    radius: 0.6,
+                    power: ExplosionPower::None,
+                });
+                punch.punched.push(projectile);
+            }
+        }
+
+        if time.reached(punch.until) {
+            commands.entity(entity).remove::<Punch>();
         }
     }
 }
@@ -93,12 +93,12 @@ pub struct HealthPlugin;
 
 impl Plugin for HealthPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<(Entity, DeathEvent)>()
-            .add_event::<(Entity, DamageEvent)>()
-            .add_event::<(Entity, ReceivedDamage)>()
-            .add_system_to_stage(CoreStage::Last, die_after)
-            .add_system(damage)
-            .add_system_to_stage(CoreStage::First, despawn_dead.exclusive_system());
+        app.add_game_event::<(Entity, DeathEvent)>()
+            .add_game_event::<(Entity, DamageEvent)>()
+            .add_game_event::<(Entity, ReceivedDamage)>()
+            .add_game_system(GameStage::Last, die_after)
+            .add_game_system(GameStage::Update, damage)
+            .add_game_system(GameStage::First, despawn_dead.exclusive_system());
     }
 }
 
@@ -1,9 +1,11 @@
 use crate::common::*;
 
 pub mod ai;
+pub mod behaviour;
 pub mod damage;
 pub mod health;
 pub mod movement;
+pub mod navigation;
 pub mod physics;
 
 pub struct MechanicsPlugin;
@@ -14,6 +16,8 @@ impl Plugin for MechanicsPlugin {
             .add_plugin(health::HealthPlugin)
             .add_plugin(damage::DamagePlugin)
             .add_plugin(movement::MovementPlugin)
-            .add_plugin(ai::AiPlugin);
+            .add_plugin(navigation::NavigationPlugin)
+            .add_plugin(ai::AiPlugin)
+            .add_plugin(behaviour::BehaviourPlugin);
     }
 }
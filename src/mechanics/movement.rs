@@ -1,6 +1,7 @@
-use super::health::DieAfter;
+use super::{health::DieAfter, navigation::NavGrid};
 use crate::{
     common::*,
+    control::rng::RngStream,
     objects::{player::Player, spawn::TemporaryWall},
     present::hud_elements::WorldText,
 };
@@ -15,6 +16,8 @@ pub struct KinematicController {
 
     // internal state
     pub dash: Option<(Vec2, Duration)>, // (dir, until)
+    /// Actual movement during the last tick
+    pub velocity: Vec2,
 }
 
 /// Entity command
@@ -35,17 +38,23 @@ pub struct MovementPlugin;
 
 impl Plugin for MovementPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<(Entity, KinematicCommand)>()
-            .add_system(kinematic_controller.label(MovementSystemLabel))
-            .add_system(drop_spread)
-            .add_system(save_from_walls);
+        app.add_game_event::<(Entity, KinematicCommand)>()
+            .add_game_system(
+                GameStage::Update,
+                kinematic_controller.label(MovementSystemLabel),
+            )
+            .add_game_system(GameStage::Update, drop_spread)
+            .add_game_system(GameStage::Update, save_from_walls);
     }
 }
 
 // TODO: move this to player
 fn save_from_walls(
     mut commands: Commands,
-    mut players: Query<(Entity, &GlobalTransform, &mut Transform), With<KinematicController>>,
+    mut players: Query<
+        (Entity, &GlobalTransform, &mut Transform),
+        (With<KinematicController>, With<Player>),
+    >,
     walls: Query<(), With<TemporaryWall>>, phy: Res<RapierContext>,
 ) {
     for (entity, pos, mut transform) in players.iter_mut() {
@@ -90,6 +99,10 @@ fn kinematic_controller(
     )>,
     time: Res<GameTime>, mut cmds: CmdReader<KinematicCommand>, phy: Res<RapierContext>,
 ) {
+    for (.., mut kinematic) in entities.iter_mut() {
+        kinematic.velocity = Vec2::ZERO
+    }
+
     // process commands
     cmds.iter_cmd_mut(
         &mut entities,
@@ -120,6 +133,7 @@ fn kinematic_controller(
                     .is_none()
                 {
                     transform.add_2d(dir * speed);
+                    kinematic.velocity += dir * kinematic.speed;
                 } else if phy
                     .cast_ray(
                         global_pos,
@@ -131,6 +145,7 @@ fn kinematic_controller(
                     .is_none()
                 {
                     transform.add_2d(vec2(dir.x, 0.) * speed);
+                    kinematic.velocity += vec2(dir.x, 0.) * kinematic.speed;
                 } else if phy
                     .cast_ray(
                         global_pos,
@@ -142,6 +157,7 @@ fn kinematic_controller(
                     .is_none()
                 {
                     transform.add_2d(vec2(0., dir.y) * speed);
+                    kinematic.velocity += vec2(0., dir.y) * kinematic.speed;
                 }
             }
             KinematicCommand::Dash { dir } => {
@@ -168,23 +184,39 @@ fn kinematic_controller(
                     .is_none()
                 {
                     transform.add_2d(offset);
+                    kinematic.velocity += dir * speed;
                 }
             }
         }
     }
 }
 
-fn drop_spread(mut entities: Query<(&mut Transform, &mut DropSpread)>, time: Res<GameTime>) {
+fn drop_spread(
+    mut entities: Query<(&mut Transform, &mut DropSpread)>, time: Res<GameTime>,
+    mut rng: ResMut<GameRng>, grid: Res<NavGrid>, player: Query<&GlobalTransform, With<Player>>,
+) {
     let distance = 2.5; // approximate
     let duration = Duration::from_millis(1500);
 
     for (mut transform, mut spread) in entities.iter_mut() {
+        let pos = transform.pos_2d();
         let (start, dir) = spread.0.get_or_insert_with(|| {
             use rand::*;
-            (
-                time.now(),
-                Vec2::Y.rotated(thread_rng().gen_range(0. ..TAU)),
-            )
+            // player must be able to pick it up
+            let from = player
+                .get_single()
+                .map(|player| player.pos_2d())
+                .unwrap_or(pos);
+            let rng = rng.gameplay(RngStream::Loot);
+            let dir = (0..8)
+                .map(|_| Vec2::Y.rotated(rng.gen_range(0. ..TAU)))
+                .find(|dir| grid.reachable(from, pos + *dir * distance))
+                .or_else(|| {
+                    let target = grid.nearest_reachable(pos, from)?;
+                    (target - pos).try_normalize()
+                })
+                .unwrap_or(Vec2::Y);
+            (time.now(), dir)
         });
         let t = time.t_passed(*start, duration);
         if t < 1. {
@@ -0,0 +1,320 @@
+use super::movement::{KinematicCommand, KinematicController, MovementSystemLabel};
+use crate::{
+    common::*,
+    objects::{spawn::WaveEvent, waves::Arena},
+};
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+/// Resource - which parts of the arena are blocked by static obstacles.
+/// Rebuilt on each wave start.
+#[derive(Default)]
+pub struct NavGrid {
+    arena: Arena,
+    size: IVec2,
+    blocked: Vec<bool>,
+    /// Index of connected area for each free cell
+    region: Vec<Option<usize>>,
+    /// Incremented on each rebuild
+    version: usize,
+}
+
+impl NavGrid {
+    pub const CELL: f32 = 0.5;
+    /// Free space around obstacles
+    const MARGIN: f32 = 0.4;
+
+    // A* costs
+    const STRAIGHT: u32 = 10;
+    const DIAGONAL: u32 = 14;
+
+    pub fn is_free(&self, pos: Vec2) -> bool {
+        self.index(self.cell(pos))
+            .map(|index| !self.blocked[index])
+            .unwrap_or(false)
+    }
+
+    /// Is there a path between positions
+    pub fn reachable(&self, from: Vec2, to: Vec2) -> bool {
+        match (self.free_index(from), self.free_index(to)) {
+            (Some(from), Some(to)) => self.region[from] == self.region[to],
+            _ => false,
+        }
+    }
+
+    /// Closest position to the specified one which is reachable from another position
+    pub fn nearest_reachable(&self, pos: Vec2, from: Vec2) -> Option<Vec2> {
+        let region = self.region[self.free_index(from)?];
+        let start = self.cell(pos).clamp(IVec2::ZERO, self.size - 1);
+        let mut visited = vec![false; self.blocked.len()];
+        let mut queue = std::collections::VecDeque::from([start]);
+        visited[self.index(start)?] = true;
+
+        while let Some(cell) = queue.pop_front() {
+            let index = self.index(cell).unwrap();
+            if !self.blocked[index] && self.region[index] == region {
+                return Some(if cell == self.cell(pos) { pos } else { self.center(cell) });
+            }
+            for dir in [IVec2::X, -IVec2::X, IVec2::Y, -IVec2::Y] {
+                if let Some(index) = self.index(cell + dir) {
+                    if !visited[index] {
+                        visited[index] = true;
+                        queue.push_back(cell + dir);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Shortest path, excluding starting position. Ends in the target position if it's free.
+    pub fn find_path(&self, from: Vec2, to: Vec2) -> Option<Vec<Vec2>> {
+        let start = self.free_index(from)?;
+        let goal = self.free_index(to)?;
+        if self.region[start] != self.region[goal] {
+            return None;
+        }
+
+        let goal_cell = self.cell_of(goal);
+        let heuristic = |cell: IVec2| {
+            let delta = (cell - goal_cell).abs();
+            let (min, max) = (delta.min_element() as u32, delta.max_element() as u32);
+            min * Self::DIAGONAL + (max - min) * Self::STRAIGHT
+        };
+
+        let mut cost = vec![u32::MAX; self.blocked.len()];
+        let mut came_from = vec![None; self.blocked.len()];
+        let mut open = BinaryHeap::new();
+        cost[start] = 0;
+        open.push(Reverse((heuristic(self.cell_of(start)), start)));
+
+        while let Some(Reverse((_, index))) = open.pop() {
+            if index == goal {
+                break;
+            }
+            let cell = self.cell_of(index);
+            for (next, step) in self.neighbours(cell) {
+                let next_cost = cost[index] + step;
+                if next_cost < cost[next] {
+                    cost[next] = next_cost;
+                    came_from[next] = Some(index);
+                    open.push(Reverse((next_cost + heuristic(self.cell_of(next)), next)));
+                }
+            }
+        }
+        if cost[goal] == u32::MAX {
+            return None;
+        }
+
+        let end = if self.is_free(to) { to } else { self.center(goal_cell) };
+        if start == goal {
+            return Some(vec![end]);
+        }
+
+        let mut cells = vec![goal];
+        while let Some(index) = came_from[*cells.last().unwrap()] {
+            cells.push(index)
+        }
+        cells.reverse();
+
+        let mut points: Vec<_> = cells
+            .into_iter()
+            .map(|index| self.center(self.cell_of(index)))
+            .collect();
+        points[0] = from;
+        *points.last_mut().unwrap() = end;
+
+        // skip points which aren't needed to walk around obstacles
+        let mut path = vec![];
+        let mut current = 0;
+        while current + 1 < points.len() {
+            let next = (current + 1..points.len())
+                .rev()
+                .find(|next| self.line_free(points[current], points[*next]))
+                .unwrap_or(current + 1);
+            path.push(points[next]);
+            current = next;
+        }
+        Some(path)
+    }
+
+    fn rebuild(&mut self, arena: Arena, phy: &RapierContext) {
+        self.arena = arena;
+        self.size = (arena.size / Self::CELL).ceil().as_ivec2();
+        let count = (self.size.x * self.size.y) as usize;
+        self.version += 1;
+
+        let shape = Collider::ball(Self::CELL / 2. + Self::MARGIN);
+        let filter = QueryFilter::only_fixed().groups(PhysicsType::MovementController.into());
+        self.blocked = (0..count)
+            .map(|index| {
+                let pos = self.center(self.cell_of(index));
+                phy.intersection_with_shape(pos, 0., &shape, filter)
+                    .is_some()
+            })
+            .collect();
+
+        // flood fill
+        self.region = vec![None; count];
+        let mut regions = 0;
+        for start in 0..count {
+            if self.blocked[start] || self.region[start].is_some() {
+                continue;
+            }
+            self.region[start] = Some(regions);
+            let mut stack = vec![start];
+            while let Some(index) = stack.pop() {
+                let neighbours: Vec<_> = self.neighbours(self.cell_of(index)).collect();
+                for (next, _) in neighbours {
+                    if self.region[next].is_none() {
+                        self.region[next] = Some(regions);
+                        stack.push(next);
+                    }
+                }
+            }
+            regions += 1;
+        }
+    }
+
+    fn cell(&self, pos: Vec2) -> IVec2 {
+        ((pos - self.arena.offset) / Self::CELL + self.size.as_vec2() / 2.)
+            .floor()
+            .as_ivec2()
+    }
+
+    fn cell_of(&self, index: usize) -> IVec2 {
+        IVec2::new(index as i32 % self.size.x, index as i32 / self.size.x)
+    }
+
+    fn center(&self, cell: IVec2) -> Vec2 {
+        self.arena.offset + (cell.as_vec2() + 0.5 - self.size.as_vec2() / 2.) * Self::CELL
+    }
+
+    fn index(&self, cell: IVec2) -> Option<usize> {
+        (cell.cmpge(IVec2::ZERO).all() && cell.cmplt(self.size).all())
+            .then(|| (cell.x + cell.y * self.size.x) as usize)
+    }
+
+    /// Index of the closest free cell, if it's not too far
+    fn free_index(&self, pos: Vec2) -> Option<usize> {
+        let max_distance = 3;
+        let cell = self.cell(pos);
+        (0..=max_distance).find_map(|distance| {
+            (-distance..=distance)
+                .flat_map(|y| (-distance..=distance).map(move |x| IVec2::new(x, y)))
+                .filter(|delta| delta.abs().max_element() == distance)
+                .filter_map(|delta| self.index(cell + delta))
+                .find(|index| !self.blocked[*index])
+        })
+    }
+
+    /// Free adjacent cells and cost of moving there. Diagonal moves can't cut corners.
+    fn neighbours(&self, cell: IVec2) -> impl Iterator<Item = (usize, u32)> + '_ {
+        let free = move |cell: IVec2| self.index(cell).filter(|index| !self.blocked[*index]);
+        [
+            IVec2::X,
+            -IVec2::X,
+            IVec2::Y,
+            -IVec2::Y,
+            IVec2::ONE,
+            -IVec2::ONE,
+            IVec2::new(1, -1),
+            IVec2::new(-1, 1),
+        ]
+        .into_iter()
+        .filter_map(move |dir| {
+            if dir.x != 0 && dir.y != 0 {
+                free(cell + IVec2::new(dir.x, 0))?;
+                free(cell + IVec2::new(0, dir.y))?;
+                Some((free(cell + dir)?, Self::DIAGONAL))
+            } else {
+                Some((free(cell + dir)?, Self::STRAIGHT))
+            }
+        })
+    }
+
+    fn line_free(&self, from: Vec2, to: Vec2) -> bool {
+        let steps = (from.distance(to) / (Self::CELL / 2.)).ceil() as usize;
+        (0..=steps).all(|i| self.is_free(from.lerp(to, i as f32 / steps.max(1) as f32)))
+    }
+}
+
+/// Moves along the shortest path to the target using KinematicController.
+/// Path is recalculated when the target moves to another cell or the grid is rebuilt.
+#[derive(Component, Default)]
+pub struct PathTo {
+    // config
+    pub target: Option<Vec2>,
+
+    // state
+    pub path: Vec<Vec2>,
+    target_cell: Option<IVec2>,
+    version: usize,
+}
+
+#[derive(SystemLabel)]
+pub struct PathSystemLabel;
+
+//
+
+pub struct NavigationPlugin;
+
+impl Plugin for NavigationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NavGrid>()
+            .add_game_system(GameStage::PostUpdate, rebuild_grid)
+            .add_game_system(
+                GameStage::Update,
+                follow_path
+                    .label(PathSystemLabel)
+                    .before(MovementSystemLabel),
+            );
+    }
+}
+
+fn rebuild_grid(
+    mut grid: ResMut<NavGrid>, mut events: EventReader<WaveEvent>, phy: Res<RapierContext>,
+) {
+    // walls are spawned at the start of the tick, so they're already in physics world
+    if events.iter().any(|event| *event == WaveEvent::Started) {
+        grid.rebuild(Arena::default(), &phy)
+    }
+}
+
+fn follow_path(
+    mut entities: Query<(Entity, &GlobalTransform, &KinematicController, &mut PathTo)>,
+    grid: Res<NavGrid>, mut kinematic: CmdWriter<KinematicCommand>, time: Res<GameTime>,
+) {
+    for (entity, pos, controller, mut path) in entities.iter_mut() {
+        let pos = pos.pos_2d();
+        let target = match path.target {
+            Some(target) => target,
+            None => {
+                path.path.clear();
+                path.target_cell = None;
+                continue;
+            }
+        };
+
+        let target_cell = grid.cell(target);
+        if path.target_cell != Some(target_cell) || path.version != grid.version {
+            path.path = grid.find_path(pos, target).unwrap_or_default();
+            path.target_cell = Some(target_cell);
+            path.version = grid.version;
+        }
+
+        let step = controller.speed * time.delta_seconds();
+        while let Some(point) = path.path.first() {
+            if point.distance(pos) > step.max(NavGrid::CELL / 4.) {
+                break;
+            }
+            path.path.remove(0);
+        }
+        if let Some(dir) = path
+            .path
+            .first()
+            .and_then(|point| (*point - pos).try_normalize())
+        {
+            kinematic.send((entity, KinematicCommand::Move { dir }))
+        }
+    }
+}
@@ -1,15 +1,19 @@
 use crate::common::*;
 use bevy::utils::HashSet;
+use serde::Deserialize;
 
 //
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Deserialize)]
 pub enum PhysicsType {
     Solid,
     Projectile,
     MovementController,
     Hitscan,
     Loot,
+    /// Blocks only projectiles and rays of other teams. Must be a `Sensor`,
+    /// so projectiles of the same team or owner can pass through it.
+    ForceField,
 }
 
 impl PhysicsType {
@@ -19,13 +23,15 @@ impl PhysicsType {
         let mov_controller = 4;
         let hitscan = 8;
         let loot = 16;
+        let force_field = 32;
 
         let (memberships, filters) = match self {
             PhysicsType::Solid => (obstacle, obstacle | projectile | mov_controller | hitscan),
-            PhysicsType::Projectile => (projectile, obstacle | hitscan),
+            PhysicsType::Projectile => (projectile, obstacle | hitscan | force_field),
             PhysicsType::MovementController => (mov_controller, obstacle),
-            PhysicsType::Hitscan => (hitscan, obstacle | projectile),
+            PhysicsType::Hitscan => (hitscan, obstacle | projectile | force_field),
             PhysicsType::Loot => (loot, loot),
+            PhysicsType::ForceField => (force_field, projectile | hitscan),
         };
         CollisionGroups {
             memberships,
@@ -54,11 +60,40 @@ pub struct PhysicsPlugin;
 
 impl Plugin for PhysicsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_to_stage(
-            CoreStage::PreUpdate,
+        // physics runs every tick, between gameplay updates
+        app.stage(GameStage::Tick, |schedule: &mut Schedule| {
+            let stage = |stage| {
+                SystemStage::parallel()
+                    .with_system_set(RapierPhysicsPlugin::<()>::get_systems(stage))
+            };
+            schedule
+                .add_stage_after(
+                    GameStage::Update,
+                    PhysicsStages::SyncBackend,
+                    stage(PhysicsStages::SyncBackend),
+                )
+                .add_stage_after(
+                    PhysicsStages::SyncBackend,
+                    PhysicsStages::StepSimulation,
+                    stage(PhysicsStages::StepSimulation),
+                )
+                .add_stage_after(
+                    PhysicsStages::StepSimulation,
+                    PhysicsStages::Writeback,
+                    stage(PhysicsStages::Writeback),
+                )
+                .add_stage_before(
+                    GameStage::Last,
+                    PhysicsStages::DetectDespawn,
+                    stage(PhysicsStages::DetectDespawn),
+                )
+        })
+        .add_game_system(
+            GameStage::PreUpdate,
             collect_contacts_enable.exclusive_system().at_start(),
         )
-        .add_system(collect_contacts);
+        // collision events are read in the same tick they are sent
+        .add_game_system(GameStage::PostUpdate, collect_contacts);
     }
 }
 
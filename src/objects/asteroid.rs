@@ -0,0 +1,150 @@
+use super::{loot::DropsLoot, spawn::TemporaryWall, waves::LootTable};
+use crate::{
+    common::*,
+    control::rng::RngStream,
+    mechanics::{
+        damage::{DamageOnContact, Team},
+        health::{Damage, DeathEvent, Health},
+        physics::CollectContacts,
+    },
+    present::effect::{Explosion, ExplosionPower, FlashOnDamage},
+    settings::Difficulty,
+};
+use rand::Rng;
+
+/// Drifting rock. Damages everything it touches, splits in smaller ones when destroyed.
+#[derive(Component, Clone)]
+pub struct Asteroid {
+    /// How many times it will split; 0 is the smallest one
+    pub size: u32,
+    /// Rolled separately for each piece
+    pub loot: Option<LootTable>,
+}
+
+impl Asteroid {
+    const PIECES: usize = 2;
+    /// Pieces fly away from each other with that speed
+    const SPLIT_SPEED: f32 = 2.;
+
+    pub fn radius(size: u32) -> f32 {
+        0.6 * 1.5f32.powi(size as i32)
+    }
+
+    /// Proportional to the area
+    fn mass(size: u32) -> f32 {
+        Self::radius(size).powi(2) * 4.
+    }
+}
+
+//
+
+pub struct AsteroidPlugin;
+
+impl Plugin for AsteroidPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_game_system(GameStage::PostUpdate, split_asteroids);
+    }
+}
+
+pub fn create_asteroid(
+    commands: &mut Commands, origin: Vec2, velocity: Vec2, asteroid: Asteroid,
+    difficulty: Difficulty, rng: &mut impl Rng,
+) -> Entity {
+    use bevy_lyon::*;
+
+    let radius = Asteroid::radius(asteroid.size);
+    // only the looks are irregular, collider is a circle
+    let corners = 7 + asteroid.size as usize * 2;
+    let points = (0..corners)
+        .map(|i| {
+            Vec2::Y.rotated(TAU * i as f32 / corners as f32) * radius * rng.gen_range(0.8..1.1)
+        })
+        .collect();
+    let loot = asteroid
+        .loot
+        .as_ref()
+        .map(|loot| loot.roll(difficulty, rng))
+        .unwrap_or_default();
+
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &shapes::Polygon {
+                points,
+                closed: true,
+            },
+            DrawMode::Outlined {
+                fill_mode: FillMode::color(Color::rgb(0.2, 0.17, 0.15)),
+                outline_mode: StrokeMode::new(Color::GRAY, 0.08),
+            },
+            Transform::new_2d(origin),
+        ))
+        .insert(Depth::Wall)
+        .insert(FlashOnDamage::Radius(radius))
+        .insert(
+            Explosion {
+                origin: Vec2::ZERO,
+                color0: Color::GRAY,
+                color1: Color::rgb(0.4, 0.3, 0.2),
+                time: Duration::from_millis(400),
+                radius: radius * 1.5,
+                power: ExplosionPower::Small,
+            }
+            .death(),
+        )
+        //
+        .insert(GameplayObject)
+        .insert(TemporaryWall)
+        .insert(Team::Neutral)
+        .insert(Health::new(2. + asteroid.size as f32 * 3.))
+        .insert(Damage::new(1.))
+        .insert(DamageOnContact)
+        .insert(CollectContacts::default())
+        .insert(DropsLoot(loot))
+        //
+        .insert(RigidBody::Dynamic)
+        .insert(Collider::ball(radius))
+        .insert(ColliderMassProperties::Mass(Asteroid::mass(asteroid.size)))
+        .insert(Restitution::coefficient(0.8))
+        .insert(PhysicsType::Solid.rapier())
+        .insert(Velocity {
+            linvel: velocity,
+            angvel: rng.gen_range(-1. ..1.),
+        })
+        .insert(asteroid)
+        .id()
+}
+
+fn split_asteroids(
+    mut commands: Commands, mut death: CmdReader<DeathEvent>,
+    mut asteroids: Query<(&GlobalTransform, &Velocity, &Asteroid)>, settings: Res<Settings>,
+    mut rng: ResMut<GameRng>,
+) {
+    death.iter_cmd_mut(&mut asteroids, |_, (pos, velocity, asteroid)| {
+        if asteroid.size == 0 {
+            return;
+        }
+        let rng = rng.gameplay(RngStream::Spawn);
+        let size = asteroid.size - 1;
+        let radius = Asteroid::radius(size);
+
+        // pieces move in opposite directions, so they only add momentum of the whole rock
+        let momentum = velocity.linvel * Asteroid::mass(asteroid.size);
+        let linvel = momentum / (Asteroid::mass(size) * Asteroid::PIECES as f32);
+
+        let angle = rng.gen_range(0. ..TAU);
+        for i in 0..Asteroid::PIECES {
+            let dir = Vec2::Y.rotated(angle + TAU * i as f32 / Asteroid::PIECES as f32);
+            create_asteroid(
+                &mut commands,
+                pos.pos_2d() + dir * radius * 1.05,
+                linvel + dir * Asteroid::SPLIT_SPEED,
+                Asteroid {
+                    size,
+                    ..asteroid.clone()
+                },
+                settings.difficulty(),
+                rng,
+            );
+        }
+    })
+}
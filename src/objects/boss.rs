@@ -1,8 +1,14 @@
-use super::player::Player;
+use super::{
+    player::Player,
+    spawn::{SummonEvent, TurretType},
+};
 use crate::{
+    assets::{AppRonAsset, RonAsset},
     common::*,
     mechanics::{
-        damage::{BigProjectile, DamageOnContact, DamageRay, DieOnContact, Team},
+        ai::{FaceTarget, LosCheck, Target},
+        behaviour::Behaviour,
+        damage::{BigProjectile, DamageOnContact, DamageRay, DieOnContact, RayShield, Team},
         health::{Damage, DeathEvent, Health},
         physics::CollectContacts,
     },
@@ -14,134 +20,421 @@ use crate::{
         light::Light,
         sound::Sound,
     },
+    settings::Difficulty,
 };
+use bevy::reflect::TypeUuid;
+use serde::Deserialize;
+
+/// Asset - boss encounters, referred by `BossPlacement::id`
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "3e7b9c1d-52a8-4f6e-b0d4-9a1c8e2f6b53"]
+pub struct BossList {
+    pub bosses: HashMap<String, BossDef>,
+}
+
+impl RonAsset for BossList {
+    const EXTENSIONS: &'static [&'static str] = &["bosses.ron"];
+}
+
+/// Positions are relative to the boss, which is placed at the top edge of the arena
+#[derive(Deserialize)]
+pub struct BossDef {
+    #[serde(default)]
+    pub background: Option<BossBackground>,
+    /// Boss dies when all of them are destroyed
+    pub parts: Vec<BossPartDef>,
+    /// Last phase with its trigger met is active; phases are never reverted
+    pub phases: Vec<BossPhase>,
+    /// Rotating segments around the boss
+    #[serde(default)]
+    pub shields: Vec<ShieldDef>,
+    /// Vertical movement speed
+    #[serde(default = "BossDef::default_speed")]
+    pub speed: f32,
+    /// Stays where it's placed instead of moving along the top edge of the arena
+    #[serde(default)]
+    pub stationary: bool,
+}
+
+#[derive(Deserialize)]
+pub struct BossBackground {
+    pub points: Vec<Vec2>,
+    pub color: Color,
+}
+
+#[derive(Deserialize)]
+pub struct BossPartDef {
+    /// Referred by stages and phase triggers
+    pub name: String,
+    pub pos: Vec2,
+    pub shape: PartShape,
+    pub color: Color,
+    /// Reduced on easy difficulty
+    pub health: f32,
+    /// Rotates around the boss, in turns per second
+    #[serde(default)]
+    pub orbit: Option<f32>,
+    /// Invincible while any of these parts are alive
+    #[serde(default)]
+    pub shielded_by: Vec<String>,
+    /// See `BehaviourList`. Part also faces the player.
+    #[serde(default)]
+    pub behaviour: Option<String>,
+}
+
+/// Blocks projectiles, and rays which aren't powerful
+#[derive(Deserialize)]
+pub struct ShieldDef {
+    pub radius: f32,
+    pub width: f32,
+    /// Segments, spread evenly
+    pub count: usize,
+    /// Size of each segment, in degrees
+    pub arc: f32,
+    /// Turns per second
+    pub speed: f32,
+    pub color: Color,
+    /// Disappears when all of these parts are destroyed
+    #[serde(default)]
+    pub powered_by: Vec<String>,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+pub enum PartShape {
+    Circle { radius: f32 },
+    Rectangle { size: Vec2 },
+}
+
+#[derive(Deserialize)]
+pub struct BossPhase {
+    /// None for the first phase
+    #[serde(default)]
+    pub trigger: Option<PhaseTrigger>,
+    /// Repeated in order. Stages which require destroyed parts are skipped.
+    pub stages: Vec<BossStage>,
+}
+
+#[derive(Deserialize)]
+pub enum PhaseTrigger {
+    /// At most that many parts remain
+    PartsLeft(usize),
+    /// Fraction of total health of all parts
+    HealthBelow(f32),
+    PartDestroyed(String),
+}
+
+/// All stages except Move keep boss at zero height
+#[derive(Deserialize)]
+pub enum BossStage {
+    /// Moves to that height above the top edge of the arena
+    Move { y: f32, seconds: f32 },
+    /// Part charges, then fires rays moving through the whole arena
+    Sweep {
+        part: String,
+        rays: Vec<SweepRay>,
+        seconds: f32,
+    },
+    /// Parts charge, then fire guided rockets in turns
+    Rockets {
+        parts: Vec<String>,
+        count: usize,
+        interval: f32,
+    },
+    /// Part charges, then spawns turrets around itself
+    Summon {
+        part: String,
+        ty: TurretType,
+        count: usize,
+        radius: f32,
+    },
+}
+
+#[derive(Clone, Copy, Deserialize)]
+pub enum SweepRay {
+    /// Moves from the left edge to the right one
+    FromLeft,
+    FromRight,
+    /// Starts in the center and follows the player
+    Player {
+        speed: f32,
+    },
+}
 
 #[derive(Component)]
 pub struct TheBoss {
+    pub id: String,
     pub world_size: Vec2,
     pub offset: Vec2,
 }
 
+impl BossDef {
+    fn default_speed() -> f32 {
+        12.
+    }
+
+    fn part(&self, name: &str) -> Option<usize> {
+        let index = self.parts.iter().position(|part| part.name == name);
+        if index.is_none() {
+            log::error!("No boss part \"{}\"", name)
+        }
+        index
+    }
+}
+
+impl PartShape {
+    fn radius(self) -> f32 {
+        match self {
+            PartShape::Circle { radius } => radius,
+            PartShape::Rectangle { size } => size.max_element(),
+        }
+    }
+}
+
+impl ShieldDef {
+    /// Segment pointing up
+    fn points(&self) -> Vec<Vec2> {
+        let arc = self.arc.to_radians();
+        let steps = (self.arc / 10.).ceil().max(1.) as usize;
+        let point = |i: usize, radius: f32| {
+            Vec2::Y.rotated(lerp(-arc / 2., arc / 2., i as f32 / steps as f32)) * radius
+        };
+        let outer = (0..=steps).map(|i| point(i, self.radius + self.width / 2.));
+        let inner = (0..=steps)
+            .rev()
+            .map(|i| point(i, self.radius - self.width / 2.));
+        outer.chain(inner).collect()
+    }
+}
+
+impl PhaseTrigger {
+    fn is_met(&self, parts_left: usize, health: f32, is_alive: impl Fn(&str) -> bool) -> bool {
+        match self {
+            PhaseTrigger::PartsLeft(count) => parts_left <= *count,
+            PhaseTrigger::HealthBelow(fraction) => health < *fraction,
+            PhaseTrigger::PartDestroyed(name) => !is_alive(name),
+        }
+    }
+}
+
+impl BossStage {
+    const CHARGE_DURATION: Duration = Duration::from_millis(1000);
+
+    fn duration(&self) -> Duration {
+        match self {
+            BossStage::Move { seconds, .. } => Duration::from_secs_f32(*seconds),
+            BossStage::Sweep { seconds, .. } => {
+                Self::CHARGE_DURATION + Duration::from_secs_f32(*seconds)
+            }
+            BossStage::Rockets {
+                count, interval, ..
+            } => Self::CHARGE_DURATION + Duration::from_secs_f32(*interval) * *count as u32,
+            BossStage::Summon { .. } => Self::CHARGE_DURATION + Duration::from_millis(500),
+        }
+    }
+
+    fn y(&self) -> f32 {
+        match self {
+            BossStage::Move { y, .. } => *y,
+            _ => 0.,
+        }
+    }
+
+    /// Parts which charge at the start. Stage is skipped if all of them are destroyed.
+    fn parts(&self) -> Vec<&str> {
+        match self {
+            BossStage::Move { .. } => vec![],
+            BossStage::Sweep { part, .. } | BossStage::Summon { part, .. } => vec![part],
+            BossStage::Rockets { parts, .. } => parts.iter().map(String::as_str).collect(),
+        }
+    }
+
+    fn charge_color(&self) -> Color {
+        match self {
+            BossStage::Move { .. } => Color::NONE,
+            BossStage::Sweep { .. } => Color::rgb(1., 0.4, 0.3),
+            BossStage::Rockets { .. } => Color::rgb(0.8, 1., 1.),
+            BossStage::Summon { .. } => Color::rgb(1., 0.8, 0.3),
+        }
+    }
+}
+
 //
 
 pub struct BossPlugin;
 
 impl Plugin for BossPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(the_boss_spawn.exclusive_system())
-            .add_system_to_stage(CoreStage::PostUpdate, boss_destruction.exclusive_system())
-            .add_system(the_boss_logic)
-            .add_system(update_guided_rocket);
+        app.add_ron_asset::<BossList>()
+            .add_game_system(GameStage::Update, the_boss_spawn.exclusive_system())
+            .add_game_system(GameStage::PostUpdate, boss_destruction.exclusive_system())
+            .add_game_system(GameStage::Update, the_boss_logic)
+            .add_game_system(GameStage::Update, boss_shields)
+            .add_game_system(GameStage::Update, orbit)
+            .add_game_system(GameStage::Update, update_guided_rocket);
     }
 }
 
 fn the_boss_spawn(
-    mut commands: Commands, boss: Query<(Entity, &TheBoss), Added<TheBoss>>,
-    settings: Res<Settings>, time: Res<GameTime>,
+    mut commands: Commands, boss: Query<(Entity, &TheBoss), Without<BossState>>,
+    settings: Res<Settings>, lists: Res<Assets<BossList>>, assets: Res<MyAssets>,
 ) {
     use bevy_lyon::*;
+
+    let list = match lists.get(&assets.bosses) {
+        Some(list) => list,
+        None => return,
+    };
     for (entity, boss) in boss.iter() {
-        let center_radius = 1.;
-        let tower_distance = 8.;
-        let tower_width = 1.5;
-        let tower_length = 2.;
+        let def = match list.bosses.get(&boss.id) {
+            Some(def) => def,
+            None => {
+                log::error!("No boss \"{}\"", boss.id);
+                commands.entity(entity).despawn_recursive();
+                continue;
+            }
+        };
+
+        let (health_factor, accuracy) = match settings.difficulty() {
+            Difficulty::Easy => (0.6, 0.5),
+            Difficulty::Hard => (1., 0.8),
+        };
+        let outline_mode = StrokeMode::new(Color::rgb(0.8, 0.8, 0.8), 0.1);
 
         commands
             .entity(entity)
             .insert(BossState {
-                parts: 3,
+                parts: def.parts.len(),
                 die_start: None,
                 count: -1,
+                extent: def
+                    .parts
+                    .iter()
+                    .map(|part| part.pos.length() + part.shape.radius())
+                    .fold(0., f32::max)
+                    + 3.,
             })
             .insert(BossLogic {
-                start: time.now(),
                 ray_min: boss.offset.x - boss.world_size.x / 2.,
                 ray_max: boss.offset.x + boss.world_size.x / 2.,
-                x_tower: tower_distance,
+                max_health: def.parts.iter().map(|part| part.health).sum::<f32>() * health_factor,
                 ..default()
             })
             .with_children(|parent| {
-                let outline_mode = StrokeMode::new(Color::rgb(0.8, 0.8, 0.8), 0.1);
-                let health = 40.
-                    * match settings.difficulty {
-                        crate::settings::Difficulty::Easy => 0.6,
-                        crate::settings::Difficulty::Hard => 1.,
-                    };
-
-                // background
-                parent
-                    .spawn_bundle(GeometryBuilder::build_as(
-                        &shapes::Polygon {
-                            points: vec![
-                                vec2(-(tower_distance + tower_width), 50.),
-                                vec2(-(tower_distance + tower_width), -1.),
-                                vec2(tower_distance + tower_width, -1.),
-                                vec2(tower_distance + tower_width, 50.),
-                            ],
-                            closed: true,
-                        },
-                        DrawMode::Outlined {
-                            fill_mode: FillMode::color(Color::rgb(0.2, 0., 0.)),
-                            outline_mode,
-                        },
-                        default(),
-                    ))
-                    .insert(Depth::BossBackground);
-
-                // center
-                parent
-                    .spawn_bundle(GeometryBuilder::build_as(
-                        &shapes::Circle {
-                            radius: center_radius,
-                            center: Vec2::ZERO,
-                        },
-                        DrawMode::Outlined {
-                            fill_mode: FillMode::color(Color::rgb(1., 0.3, 0.3)),
-                            outline_mode,
-                        },
-                        Transform::new_2d(vec2(0., -center_radius * 0.2 - 1.)),
-                    ))
-                    .insert(Depth::Boss)
-                    .insert(SpawnEffect {
-                        radius: center_radius,
-                    })
-                    .insert(FlashOnDamage::Radius(center_radius))
-                    //
-                    .insert(Team::Enemy)
-                    .insert(Health::new(health * 2.))
-                    .insert(RigidBody::KinematicPositionBased)
-                    .insert(PhysicsType::Solid.rapier())
-                    .insert(Collider::ball(center_radius))
-                    //
-                    .insert(BossPart(entity, 0));
-
-                // towers
-                for (x, id) in [(-tower_distance, 1), (tower_distance, 2)] {
+                if let Some(background) = &def.background {
                     parent
                         .spawn_bundle(GeometryBuilder::build_as(
-                            &shapes::Rectangle {
-                                extents: vec2(tower_width, tower_length),
-                                origin: RectangleOrigin::Center,
+                            &shapes::Polygon {
+                                points: background.points.clone(),
+                                closed: true,
                             },
                             DrawMode::Outlined {
-                                fill_mode: FillMode::color(Color::rgb(0.8, 0.8, 1.)),
+                                fill_mode: FillMode::color(background.color),
                                 outline_mode,
                             },
-                            Transform::new_2d(vec2(x, -1.)),
+                            default(),
                         ))
+                        .insert(Depth::BossBackground);
+                }
+
+                for (index, part) in def.parts.iter().enumerate() {
+                    let draw_mode = DrawMode::Outlined {
+                        fill_mode: FillMode::color(part.color),
+                        outline_mode,
+                    };
+                    let transform = Transform::new_2d(part.pos);
+                    let (bundle, collider) = match part.shape {
+                        PartShape::Circle { radius } => (
+                            GeometryBuilder::build_as(
+                                &shapes::Circle {
+                                    radius,
+                                    center: Vec2::ZERO,
+                                },
+                                draw_mode,
+                                transform,
+                            ),
+                            Collider::ball(radius),
+                        ),
+                        PartShape::Rectangle { size } => (
+                            GeometryBuilder::build_as(
+                                &shapes::Rectangle {
+                                    extents: size,
+                                    origin: RectangleOrigin::Center,
+                                },
+                                draw_mode,
+                                transform,
+                            ),
+                            Collider::cuboid(size.x / 2., size.y / 2.),
+                        ),
+                    };
+                    let radius = part.shape.radius();
+
+                    let mut commands = parent.spawn_bundle(bundle);
+                    if let Some(turns) = part.orbit {
+                        commands.insert(Orbit {
+                            pos: part.pos,
+                            angle: 0.,
+                            speed: turns * TAU,
+                            rotate: false,
+                        });
+                    }
+                    if let Some(id) = &part.behaviour {
+                        commands
+                            .insert(Target::Player)
+                            .insert(LosCheck::default())
+                            .insert(FaceTarget {
+                                rotation_speed: TAU * 0.5,
+                                accuracy,
+                                ..default()
+                            })
+                            .insert(Behaviour::new(id));
+                    }
+                    commands
                         .insert(Depth::Boss)
-                        .insert(SpawnEffect {
-                            radius: tower_width.max(tower_length),
-                        })
-                        .insert(FlashOnDamage::Radius(tower_width.max(tower_length)))
+                        .insert(SpawnEffect { radius })
+                        .insert(FlashOnDamage::Radius(radius))
                         //
                         .insert(Team::Enemy)
-                        .insert(Health::new(health))
+                        .insert(Health::new(part.health * health_factor))
                         .insert(RigidBody::KinematicPositionBased)
                         .insert(PhysicsType::Solid.rapier())
-                        .insert(Collider::cuboid(tower_width / 2., tower_length / 2.))
+                        .insert(collider)
                         //
-                        .insert(BossPart(entity, id));
+                        .insert(BossPart(entity, index));
+                }
+
+                for (index, shield) in def.shields.iter().enumerate() {
+                    let points = shield.points();
+                    for i in 0..shield.count {
+                        let angle = TAU * i as f32 / shield.count as f32;
+                        parent
+                            .spawn_bundle(GeometryBuilder::build_as(
+                                &shapes::Polygon {
+                                    points: points.clone(),
+                                    closed: true,
+                                },
+                                DrawMode::Fill(FillMode::color(shield.color)),
+                                default(),
+                            ))
+                            .insert(Depth::Boss)
+                            .insert(Orbit {
+                                pos: Vec2::ZERO,
+                                angle,
+                                speed: shield.speed * TAU,
+                                rotate: true,
+                            })
+                            //
+                            .insert(Team::Enemy)
+                            .insert(RayShield)
+                            .insert(RigidBody::KinematicPositionBased)
+                            .insert(Sensor)
+                            .insert(PhysicsType::ForceField.rapier())
+                            .insert(Collider::polyline(
+                                points.iter().chain(points.first()).copied().collect(),
+                                None,
+                            ))
+                            //
+                            .insert(BossShield(entity, index));
+                    }
                 }
             });
     }
@@ -149,26 +442,41 @@ fn the_boss_spawn(
 
 #[derive(Component)]
 struct BossState {
-    parts: i32,
+    parts: usize,
     die_start: Option<Duration>,
     count: i32,
+    /// Horizontal half-size of area with death explosions
+    extent: f32,
 }
 
+/// (boss, index in `BossDef::parts`)
+#[derive(Component)]
+struct BossPart(Entity, usize);
+
+/// (boss, index in `BossDef::shields`)
+#[derive(Component)]
+struct BossShield(Entity, usize);
+
+/// Rotates around the parent
 #[derive(Component)]
-struct BossPart(Entity, u8);
+struct Orbit {
+    pos: Vec2,
+    angle: f32,
+    /// Radians per second
+    speed: f32,
+    /// Also turn to keep the same side facing the parent
+    rotate: bool,
+}
 
 fn boss_destruction(
     mut commands: Commands, mut death: CmdReader<DeathEvent>, mut parts: Query<&BossPart>,
     mut bosses: Query<(Entity, &mut BossState, &GlobalTransform)>, time: Res<GameTime>,
-    mut explode: EventWriter<Explosion>,
+    mut explode: EventWriter<Explosion>, mut rng: ResMut<GameRng>,
 ) {
-    let tower_distance = 6.;
-    let tower_width = 1.5;
-
     death.iter_cmd_mut(&mut parts, |_, part| {
         if let Ok((_, mut boss, _)) = bosses.get_mut(part.0) {
-            boss.parts -= 1;
-            if boss.parts <= 0 {
+            boss.parts = boss.parts.saturating_sub(1);
+            if boss.parts == 0 {
                 boss.die_start.get_or_insert(time.now());
             }
         }
@@ -185,17 +493,17 @@ fn boss_destruction(
                     boss.count = new_count;
 
                     use rand::*;
+                    let rng = rng.cosmetic();
                     explode.send(Explosion {
                         origin: pos.pos_2d()
                             + vec2(
-                                thread_rng().gen_range(-1. ..1.)
-                                    * (tower_distance + tower_width + 3.),
-                                thread_rng().gen_range(-3. ..1.),
+                                rng.gen_range(-1. ..1.) * boss.extent,
+                                rng.gen_range(-3. ..1.),
                             ),
                         color0: Color::YELLOW,
                         color1: Color::RED,
-                        time: Duration::from_millis(thread_rng().gen_range(400..700)),
-                        radius: thread_rng().gen_range(2. ..5.),
+                        time: Duration::from_millis(rng.gen_range(400..700)),
+                        radius: rng.gen_range(2. ..5.),
                         power: ExplosionPower::Small,
                     });
                 }
@@ -209,241 +517,325 @@ struct BossLogic {
     start: Duration,
     ray_min: f32,
     ray_max: f32,
-    x_tower: f32,
+    max_health: f32,
 
-    stage: usize,
+    phase: usize,
+    /// None until first stage starts
+    stage: Option<usize>,
     count: usize,
 }
 
-#[derive(Clone, Copy)]
-enum BossStage {
-    Wait,
-    Sweep(usize),
-    Rocket,
-    Open,
-}
-
 fn the_boss_logic(
-    mut commands: Commands,
-    mut logic: Query<(&GlobalTransform, &mut Transform, &mut BossLogic, &TheBoss)>,
-    time: Res<GameTime>, parts: Query<(Entity, &BossPart)>, mut sound: EventWriter<Sound>,
-    assets: Res<MyAssets>,
+    mut commands: Commands, mut logic: Query<(Entity, &mut Transform, &mut BossLogic, &TheBoss)>,
+    time: Res<GameTime>, parts: Query<(Entity, &BossPart, &Health, &Transform), Without<TheBoss>>,
+    mut sound: EventWriter<Sound>, assets: Res<MyAssets>, lists: Res<Assets<BossList>>,
+    mut summon: EventWriter<SummonEvent>,
 ) {
-    let charge_duration = Duration::from_millis(1000);
-    let wait_duration = Duration::from_millis(1500);
-    let sweep_duration = Duration::from_millis(2000);
-    let targeted_speed = 7.5;
-    let rocket_count = 10;
-    let rocket_wait = Duration::from_millis(750);
-    let open_duration = Duration::from_millis(3500);
-    let y_speed = 12.;
-
-    let stages = [
-        BossStage::Wait,
-        BossStage::Sweep(0),
-        BossStage::Sweep(1),
-        BossStage::Sweep(2),
-        BossStage::Sweep(3),
-        BossStage::Wait,
-        BossStage::Rocket,
-        BossStage::Wait,
-        BossStage::Open,
-    ];
-    let get_part = |id: u8| parts.iter().find(|v| v.1 .1 == id).map(|v| v.0);
-
-    for (pos, mut transform, mut logic, bossinfo) in logic.iter_mut() {
-        let pos = pos.pos_2d();
-        let mut target_y = 0.;
-
-        let duration = match stages[logic.stage] {
-            BossStage::Wait => {
-                target_y = 5.;
-                wait_duration
-            }
-            BossStage::Sweep(which) => {
-                if time.passed(logic.start) >= charge_duration && logic.count == 0 {
-                    logic.count = 1;
-
-                    let mut rays = vec![];
-                    let speed = (logic.ray_max - logic.ray_min) / sweep_duration.as_secs_f32();
-                    match which {
-                        0 => rays.push((logic.ray_min, Ray::Speed(speed))),
-                        1 => rays.push((logic.ray_max, Ray::Speed(-speed))),
-                        2 => rays.push((logic.ray_min, Ray::Speed(speed))),
-                        _ => rays.push((0., Ray::Player(targeted_speed))),
-                    }
-                    if which == 2 {
-                        rays.push((logic.ray_max, Ray::Speed(-speed)))
-                    }
-                    for (pos, ray) in rays {
-                        let fade_time = Duration::from_millis(250);
-                        commands
-                            .spawn_bundle(SpatialBundle::from_transform({
-                                let mut t = Transform::new_2d(vec2(pos, transform.translation.y));
-                                t.set_angle_2d(-TAU / 2.);
-                                t
-                            }))
-                            .insert(GameplayObject)
-                            .insert(Depth::ImportantEffect)
-                            .insert(RayEffect {
-                                color: Color::RED,
-                                length: 50.,
-                                width: 1.,
-                                duration: sweep_duration - fade_time,
-                                fade_time,
-                                destroy_parent: true,
-                                ..default()
-                            })
-                            .insert(ray)
-                            .insert(Team::Enemy)
-                            .insert(Damage::new(1.))
-                            .insert(DamageRay {
-                                explosion_effect: Some(Explosion {
-                                    color0: Color::RED,
-                                    color1: Color::RED,
-                                    time: Duration::from_millis(200),
-                                    radius: 0.9,
-                                    ..default()
-                                }),
-                                ignore_obstacles: true,
+    let list = match lists.get(&assets.bosses) {
+        Some(list) => list,
+        None => return,
+    };
+
+    for (entity, mut transform, mut logic, bossinfo) in logic.iter_mut() {
+        let def = match list.bosses.get(&bossinfo.id) {
+            Some(def) => def,
+            None => continue,
+        };
+        let pos = transform.pos_2d();
+
+        // (entity, index, position relative to the boss)
+        let alive: Vec<_> = parts
+            .iter()
+            .filter(|(_, part, ..)| part.0 == entity)
+            .map(|(part_entity, part, _, transform)| (part_entity, part.1, transform.pos_2d()))
+            .collect();
+        let get_part = |name: &str| {
+            let index = def.part(name)?;
+            alive
+                .iter()
+                .find(|(_, part, _)| *part == index)
+                .map(|(entity, ..)| *entity)
+        };
+        let part_pos = |index: usize| {
+            alive
+                .iter()
+                .find(|(_, part, _)| *part == index)
+                .map(|(.., offset)| pos + *offset)
+        };
+
+        // switch phase
+        let health = parts
+            .iter()
+            .filter(|(_, part, ..)| part.0 == entity)
+            .map(|(_, _, health, _)| health.value)
+            .sum::<f32>()
+            / logic.max_health.max(1.);
+        if let Some(phase) = def
+            .phases
+            .iter()
+            .enumerate()
+            .skip(logic.phase + 1)
+            .filter(|(_, phase)| {
+                phase.trigger.as_ref().map_or(false, |trigger| {
+                    trigger.is_met(alive.len(), health, |name| get_part(name).is_some())
+                })
+            })
+            .last()
+            .map(|(index, _)| index)
+        {
+            logic.phase = phase;
+            logic.stage = None;
+        }
+        let stages = match def.phases.get(logic.phase) {
+            Some(phase) => &phase.stages,
+            None => continue,
+        };
+
+        // current stage
+        let passed = time.passed(logic.start);
+        let charged = passed >= BossStage::CHARGE_DURATION;
+        match logic.stage.map(|stage| &stages[stage]) {
+            Some(BossStage::Sweep { rays, seconds, .. }) if charged && logic.count == 0 => {
+                logic.count = 1;
+
+                let duration = Duration::from_secs_f32(*seconds);
+                let speed = (logic.ray_max - logic.ray_min) / seconds;
+                for ray in rays {
+                    let (x, ray) = match *ray {
+                        SweepRay::FromLeft => (logic.ray_min, Ray::Speed(speed)),
+                        SweepRay::FromRight => (logic.ray_max, Ray::Speed(-speed)),
+                        SweepRay::Player { speed } => (0., Ray::Player(speed)),
+                    };
+                    let fade_time = Duration::from_millis(250);
+                    commands
+                        .spawn_bundle(SpatialBundle::from_transform({
+                            let mut t = Transform::new_2d(vec2(x, transform.translation.y));
+                            t.set_angle_2d(-TAU / 2.);
+                            t
+                        }))
+                        .insert(GameplayObject)
+                        .insert(Depth::ImportantEffect)
+                        .insert(RayEffect {
+                            color: Color::RED,
+                            length: 50.,
+                            width: 1.,
+                            duration: duration.saturating_sub(fade_time),
+                            fade_time,
+                            destroy_parent: true,
+                            ..default()
+                        })
+                        .insert(ray)
+                        .insert(Team::Enemy)
+                        .insert(Damage::new(1.))
+                        .insert(DamageRay {
+                            explosion_effect: Some(Explosion {
+                                color0: Color::RED,
+                                color1: Color::RED,
+                                time: Duration::from_millis(200),
+                                radius: 0.9,
                                 ..default()
-                            })
-                            .insert(DontSparkMe);
-                    }
+                            }),
+                            ignore_obstacles: true,
+                            ..default()
+                        })
+                        .insert(DontSparkMe);
                 }
-                sweep_duration + charge_duration
             }
-            BossStage::Rocket => {
-                if time.passed(logic.start) >= charge_duration {
-                    let count = ((time.passed(logic.start) - charge_duration).as_micros()
-                        / rocket_wait.as_micros()) as usize
-                        + 1;
-                    if count != logic.count {
-                        logic.count = count;
-
-                        if let Some(_) = get_part((logic.count % 2) as u8) {
-                            let x =
-                                if logic.count % 2 == 0 { logic.x_tower } else { -logic.x_tower };
-
-                            use bevy_lyon::*;
-                            let radius = 0.5;
-                            commands
-                                .spawn_bundle(GeometryBuilder::build_as(
-                                    &shapes::Circle {
-                                        radius,
-                                        center: Vec2::ZERO,
-                                    },
-                                    DrawMode::Fill(FillMode::color(Color::WHITE)),
-                                    {
-                                        let mut t = Transform::new_2d(vec2(
-                                            x,
-                                            transform.translation.y - radius - 3.,
-                                        ));
-                                        t.set_angle_2d(-TAU / 2.);
-                                        t
-                                    },
-                                ))
-                                .insert(Depth::Projectile)
-                                .insert(Light {
-                                    radius: 2.,
-                                    color: Color::WHITE.with_a(0.07),
-                                })
-                                .insert(
-                                    Explosion {
-                                        origin: Vec2::ZERO,
-                                        color0: Color::GREEN,
-                                        color1: Color::YELLOW,
-                                        time: Duration::from_millis(400),
-                                        radius: 0.5,
-                                        power: ExplosionPower::Small,
-                                    }
-                                    .death(),
-                                )
-                                //
-                                .insert(GameplayObject)
-                                .insert(Damage::new(1.))
-                                .insert(Team::Enemy)
-                                .insert(DamageOnContact)
-                                .insert(DieOnContact)
-                                .insert(BigProjectile)
-                                .insert(CollectContacts::default())
-                                .insert(Health::new(2.))
-                                //
-                                .insert(RigidBody::Dynamic)
-                                .insert(Collider::ball(radius))
-                                .insert(ColliderMassProperties::Mass(3.))
-                                .insert(PhysicsType::Projectile.rapier())
-                                .insert(Velocity::linear(-Vec2::Y))
-                                .insert(GuidedRocket {
-                                    speed: 10.,
-                                    accel: 12.,
-                                });
-                        }
+            Some(BossStage::Rockets {
+                parts: names,
+                count,
+                interval,
+            }) if charged => {
+                let interval = Duration::from_secs_f32(*interval);
+                let new_count = ((passed - BossStage::CHARGE_DURATION).as_micros()
+                    / interval.as_micros().max(1)) as usize
+                    + 1;
+                if new_count != logic.count && new_count <= *count {
+                    logic.count = new_count;
+
+                    let launchers: Vec<_> = names
+                        .iter()
+                        .filter_map(|name| def.part(name))
+                        .filter_map(|index| Some((index, part_pos(index)?)))
+                        .collect();
+                    if !launchers.is_empty() {
+                        let (index, part_pos) = launchers[logic.count % launchers.len()];
+                        spawn_rocket(&mut commands, part_pos, def.parts[index].shape.radius());
                     }
                 }
-                rocket_count * rocket_wait + charge_duration
             }
-            BossStage::Open => {
-                target_y = -1.;
-                open_duration
+            Some(BossStage::Summon {
+                part,
+                ty,
+                count,
+                radius,
+            }) if charged && logic.count == 0 => {
+                logic.count = 1;
+                if let Some(origin) = def.part(part).and_then(part_pos) {
+                    summon.send(SummonEvent {
+                        origin,
+                        ty: *ty,
+                        count: *count,
+                        radius: *radius,
+                    });
+                }
             }
-        };
-        if time.reached(logic.start + duration) {
-            loop {
-                logic.stage = (logic.stage + 1) % stages.len();
-                match stages[logic.stage] {
-                    BossStage::Wait | BossStage::Open => break,
-                    BossStage::Sweep(_) => {
-                        if let Some(entity) = get_part(0) {
-                            commands.entity(entity).insert(ChargingAttack {
-                                radius: 3.,
-                                duration: charge_duration,
-                                color: Color::rgb(1., 0.4, 0.3),
-                            });
-                            sound.send(Sound {
-                                sound: assets.ray_charge.clone(),
-                                position: Some(pos),
-                                ..default()
-                            });
+            _ => (),
+        }
 
-                            logic.count = 0;
-                            break;
-                        }
-                    }
-                    BossStage::Rocket => {
-                        let mut any = false;
-                        for entity in [get_part(1), get_part(2)] {
-                            if let Some(entity) = entity {
-                                commands.entity(entity).insert(ChargingAttack {
-                                    radius: 3.,
-                                    duration: charge_duration,
-                                    color: Color::rgb(0.8, 1., 1.),
-                                });
-                                sound.send(Sound {
-                                    sound: assets.ray_charge.clone(),
-                                    position: Some(pos),
-                                    ..default()
-                                });
-
-                                any = true;
-                            }
-                        }
-                        if any {
-                            break;
-                        }
-                    }
+        // next stage
+        let finished = match logic.stage {
+            Some(stage) => time.reached(logic.start + stages[stage].duration()),
+            None => true,
+        };
+        if finished {
+            let first = logic.stage.map_or(0, |stage| stage + 1);
+            let next = (first..first + stages.len())
+                .map(|index| index % stages.len())
+                .find(|index| {
+                    let parts = stages[*index].parts();
+                    parts.is_empty() || parts.iter().any(|name| get_part(name).is_some())
+                });
+            if let Some(next) = next {
+                let stage = &stages[next];
+                for entity in stage.parts().into_iter().filter_map(get_part) {
+                    commands.entity(entity).insert(ChargingAttack {
+                        radius: 3.,
+                        duration: BossStage::CHARGE_DURATION,
+                        color: stage.charge_color(),
+                    });
+                    sound.send(Sound {
+                        sound: assets.ray_charge.clone(),
+                        position: Some(pos),
+                        ..default()
+                    });
                 }
             }
+            logic.stage = next;
             logic.start = time.now();
+            logic.count = 0;
         }
 
-        let target_y = bossinfo.world_size.y * 0.5 + target_y;
-        let max_y_delta = y_speed * time.delta_seconds();
-        let y_delta = (target_y - transform.translation.y).clamp(-max_y_delta, max_y_delta);
-        transform.translation.y += y_delta;
+        if !def.stationary {
+            let target_y = bossinfo.world_size.y * 0.5
+                + logic
+                    .stage
+                    .map(|stage| stages[stage].y())
+                    .unwrap_or_default();
+            let max_y_delta = def.speed * time.delta_seconds();
+            let y_delta = (target_y - transform.translation.y).clamp(-max_y_delta, max_y_delta);
+            transform.translation.y += y_delta;
+        }
     }
 }
 
+fn boss_shields(
+    mut commands: Commands, bosses: Query<&TheBoss>, mut parts: Query<(&BossPart, &mut Health)>,
+    shields: Query<(Entity, &BossShield)>, lists: Res<Assets<BossList>>, assets: Res<MyAssets>,
+) {
+    let list = match lists.get(&assets.bosses) {
+        Some(list) => list,
+        None => return,
+    };
+    let get_def = |boss: Entity| {
+        bosses
+            .get(boss)
+            .ok()
+            .and_then(|boss| list.bosses.get(&boss.id))
+    };
+
+    // (boss, index)
+    let alive: Vec<_> = parts.iter().map(|(part, _)| (part.0, part.1)).collect();
+    let any_alive = |boss: Entity, def: &BossDef, names: &[String]| {
+        names
+            .iter()
+            .filter_map(|name| def.part(name))
+            .any(|index| alive.contains(&(boss, index)))
+    };
+
+    for (part, mut health) in parts.iter_mut() {
+        if let Some(def) = get_def(part.0) {
+            let shielded_by = &def.parts[part.1].shielded_by;
+            if !shielded_by.is_empty() {
+                health.invincible = any_alive(part.0, def, shielded_by)
+            }
+        }
+    }
+    for (entity, shield) in shields.iter() {
+        if let Some(def) = get_def(shield.0) {
+            let powered_by = &def.shields[shield.1].powered_by;
+            if !powered_by.is_empty() && !any_alive(shield.0, def, powered_by) {
+                commands.entity(entity).despawn_recursive()
+            }
+        }
+    }
+}
+
+fn orbit(mut entities: Query<(&mut Transform, &mut Orbit)>, time: Res<GameTime>) {
+    for (mut transform, mut orbit) in entities.iter_mut() {
+        orbit.angle += orbit.speed * time.delta_seconds();
+        transform.set_2d(orbit.pos.rotated(orbit.angle));
+        if orbit.rotate {
+            transform.set_angle_2d(orbit.angle)
+        }
+    }
+}
+
+/// Below the part
+fn spawn_rocket(commands: &mut Commands, part_pos: Vec2, part_radius: f32) {
+    use bevy_lyon::*;
+
+    let radius = 0.5;
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &shapes::Circle {
+                radius,
+                center: Vec2::ZERO,
+            },
+            DrawMode::Fill(FillMode::color(Color::WHITE)),
+            {
+                let mut t = Transform::new_2d(part_pos - vec2(0., part_radius / 2. + radius + 1.));
+                t.set_angle_2d(-TAU / 2.);
+                t
+            },
+        ))
+        .insert(Depth::Projectile)
+        .insert(Light {
+            radius: 2.,
+            color: Color::WHITE.with_a(0.07),
+        })
+        .insert(
+            Explosion {
+                origin: Vec2::ZERO,
+                color0: Color::GREEN,
+                color1: Color::YELLOW,
+                time: Duration::from_millis(400),
+                radius: 0.5,
+                power: ExplosionPower::Small,
+            }
+            .death(),
+        )
+        //
+        .insert(GameplayObject)
+        .insert(Damage::new(1.))
+        .insert(Team::Enemy)
+        .insert(DamageOnContact)
+        .insert(DieOnContact)
+        .insert(BigProjectile)
+        .insert(CollectContacts::default())
+        .insert(Health::new(2.))
+        //
+        .insert(RigidBody::Dynamic)
+        .insert(Collider::ball(radius))
+        .insert(ColliderMassProperties::Mass(3.))
+        .insert(PhysicsType::Projectile.rapier())
+        .insert(Velocity::linear(-Vec2::Y))
+        .insert(GuidedRocket {
+            speed: 10.,
+            accel: 12.,
+        });
+}
+
 #[derive(Component)]
 struct GuidedRocket {
     speed: f32,
@@ -457,7 +849,7 @@ enum Ray {
 }
 
 fn update_guided_rocket(
-    mut rockets: Query<(&GlobalTransform, &mut Velocity, &GuidedRocket)>,
+    mut rockets: Query<(&GlobalTransform, &mut Velocity, &GuidedRocket, &Team)>,
     player: Query<&GlobalTransform, With<Player>>, time: Res<GameTime>,
     mut rays: Query<(&mut Transform, &Ray)>,
 ) {
@@ -471,7 +863,11 @@ fn update_guided_rocket(
         Ok(pos) => pos.pos_2d(),
         Err(_) => return,
     };
-    for (pos, mut velocity, rocket) in rockets.iter_mut() {
+    for (pos, mut velocity, rocket, team) in rockets.iter_mut() {
+        // deflected by the player
+        if team.is_player() {
+            continue;
+        }
         let target = (target - pos.pos_2d()).clamp_length(0., rocket.speed);
         let delta = (target - velocity.linvel).clamp_length(0., rocket.accel);
         velocity.linvel += delta * time.delta_seconds();
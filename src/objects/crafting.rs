@@ -0,0 +1,126 @@
+use super::{loot::CraftPart, stats::Stats, weapon::CraftedWeapon};
+use crate::{
+    assets::{AppRonAsset, RonAsset},
+    common::*,
+    present::sound::Sound,
+};
+use bevy::reflect::TypeUuid;
+use enum_map::EnumMap;
+use serde::Deserialize;
+
+/// Asset - all crafting recipes, in the order they are shown
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "5d3c4a8e-7a51-4f0e-9a44-2f3c0f1b6c21"]
+pub struct RecipeBook {
+    pub recipes: Vec<Recipe>,
+}
+
+impl RonAsset for RecipeBook {
+    const EXTENSIONS: &'static [&'static str] = &["recipes.ron"];
+}
+
+impl RecipeBook {
+    /// Recipes available to the player
+    pub fn known(&self, stats: &Stats) -> Vec<&Recipe> {
+        self.recipes
+            .iter()
+            .filter(|recipe| recipe.is_known(stats))
+            .collect()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Recipe {
+    /// Must be unique
+    pub name: String,
+    pub result: CraftedWeapon,
+    /// Required count of each part
+    pub parts: Vec<(CraftPart, usize)>,
+    /// Overrides default number of uses of the weapon
+    #[serde(default)]
+    pub uses: Option<f32>,
+    #[serde(default)]
+    pub unlock: RecipeUnlock,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+pub enum RecipeUnlock {
+    /// Known from the start
+    #[default]
+    Known,
+    /// Known after reaching wave with that index (starting from 0)
+    Wave(usize),
+    /// Discovered when player has all required parts at once
+    Discover,
+}
+
+impl Recipe {
+    pub fn is_known(&self, stats: &Stats) -> bool {
+        match self.unlock {
+            RecipeUnlock::Known => true,
+            RecipeUnlock::Wave(wave) => stats.wave >= wave,
+            RecipeUnlock::Discover => stats.known_recipes.contains(&self.name),
+        }
+    }
+
+    pub fn can_craft(&self, parts: &EnumMap<CraftPart, usize>) -> bool {
+        self.parts
+            .iter()
+            .all(|(part, count)| parts[*part] >= *count)
+    }
+
+    /// Removes required parts. Check `can_craft` first!
+    pub fn consume(&self, parts: &mut EnumMap<CraftPart, usize>) {
+        for (part, count) in &self.parts {
+            parts[*part] = parts[*part].saturating_sub(*count)
+        }
+    }
+
+    pub fn uses(&self) -> f32 {
+        self.uses.unwrap_or(self.result.description().2)
+    }
+
+    /// Like "Generator x1 + Laser x1"
+    pub fn parts_description(&self) -> String {
+        self.parts
+            .iter()
+            .map(|(part, count)| format!("{} x{}", part.description(), count))
+            .collect::<Vec<_>>()
+            .join(" + ")
+    }
+}
+
+//
+
+pub struct CraftingPlugin;
+
+impl Plugin for CraftingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_ron_asset::<RecipeBook>()
+            .add_game_system(GameStage::PostUpdate, discover_recipes);
+    }
+}
+
+fn discover_recipes(
+    mut stats: ResMut<Stats>, books: Res<Assets<RecipeBook>>, assets: Res<MyAssets>,
+    mut sound_cmd: EventWriter<Sound>,
+) {
+    let book = match books.get(&assets.recipes) {
+        Some(book) => book,
+        None => return,
+    };
+    for recipe in &book.recipes {
+        if matches!(recipe.unlock, RecipeUnlock::Discover)
+            && !recipe.is_known(&stats)
+            && recipe.can_craft(&stats.player.craft_parts)
+        {
+            log::info!("Discovered recipe \"{}\"", recipe.name);
+            stats.known_recipes.push(recipe.name.clone());
+            sound_cmd.send(Sound {
+                sound: assets.ui_alert.clone(),
+                non_randomized: true,
+                ..default()
+            });
+        }
+    }
+}
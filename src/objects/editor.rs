@@ -0,0 +1,555 @@
+use super::{
+    player::Player,
+    spawn::{SpawnControl, TurretType},
+    waves::{Arena, BossPlacement, TurretDefinition, WallDefinition, WaveDefinition, WaveList},
+};
+use crate::{
+    common::*, control::time::TimeMode, mechanics::health::Health, present::camera::WindowInfo,
+};
+
+/// Resource - level editor state.
+/// While it's active, edited wave is spawned instead of the normal ones.
+pub struct LevelEditor {
+    active: bool,
+    list: WaveList,
+    /// Index of the edited wave
+    wave: usize,
+    /// Name of the file in "assets/waves" directory
+    file: String,
+
+    tool: EditorTool,
+    wall_size: Vec2,
+    snap: bool,
+    paused: bool,
+    /// Player can be controlled
+    testing: bool,
+
+    selected: Option<EditorObject>,
+    /// Offset from cursor to the dragged object
+    drag: Option<Vec2>,
+}
+
+impl Default for LevelEditor {
+    fn default() -> Self {
+        Self {
+            active: false,
+            list: default(),
+            wave: 0,
+            file: "campaign.waves.ron".to_string(),
+            tool: default(),
+            wall_size: Vec2::splat(Player::DASH_DISTANCE / 2.),
+            snap: true,
+            paused: false,
+            testing: false,
+            selected: None,
+            drag: None,
+        }
+    }
+}
+
+impl LevelEditor {
+    /// Starts editing copy of the list
+    pub fn open(&mut self, mut list: WaveList) {
+        if list.waves.is_empty() {
+            list.waves.push(default())
+        }
+        *self = Self {
+            active: true,
+            list,
+            file: std::mem::take(&mut self.file),
+            ..default()
+        }
+    }
+
+    pub fn close(&mut self) {
+        self.active = false
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Wave which must be spawned
+    pub fn wave(&self) -> Option<&WaveDefinition> {
+        self.list.waves.get(self.wave).filter(|_| self.active)
+    }
+
+    fn current(&mut self) -> &mut WaveDefinition {
+        &mut self.list.waves[self.wave]
+    }
+
+    fn select_wave(&mut self, index: usize) {
+        self.wave = index.min(self.list.waves.len().saturating_sub(1));
+        self.selected = None;
+        self.drag = None;
+    }
+
+    fn to_ron(&self) -> String {
+        let config = ron::ser::PrettyConfig::new().depth_limit(4);
+        ron::ser::to_string_pretty(&self.list, config).unwrap()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save(&self) {
+        // only plain names, so it can't write outside of the directory
+        let valid = self
+            .file
+            .strip_suffix(".waves.ron")
+            .map_or(false, |name| !name.is_empty())
+            && !self.file.contains(['/', '\\', ':'])
+            && !self.file.contains("..");
+        if !valid {
+            log::error!(
+                "Invalid waves file name {:?} - must be like \"name.waves.ron\"",
+                self.file
+            );
+            return;
+        }
+        let path = std::path::Path::new("assets/waves").join(&self.file);
+        match std::fs::write(&path, self.to_ron()) {
+            Ok(_) => log::info!("Saved waves to {:?}", path),
+            Err(error) => log::error!("Failed to save waves (file: {:?}) - {}", path, error),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+enum EditorTool {
+    #[default]
+    Select,
+    Wall,
+    Turret(TurretType),
+    Boss,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EditorObject {
+    Wall(usize),
+    Turret(usize),
+    Boss,
+}
+
+impl EditorObject {
+    /// Topmost object at the world position
+    fn pick(wave: &WaveDefinition, arena: Arena, pos: Vec2) -> Option<Self> {
+        let turret_radius = 0.8;
+        let boss_radius = 2.;
+
+        for (i, turret) in wave.turrets.iter().enumerate().rev() {
+            if arena.to_world(turret.pos).distance(pos) < turret_radius {
+                return Some(Self::Turret(i));
+            }
+        }
+        if let Some(boss) = &wave.boss {
+            if arena.to_world(boss.pos).distance(pos) < boss_radius {
+                return Some(Self::Boss);
+            }
+        }
+        for (i, wall) in wave.walls.iter().enumerate().rev() {
+            let center = arena.to_world(wall.pos);
+            if pos.in_bounds(center - wall.size / 2., center + wall.size / 2.) {
+                return Some(Self::Wall(i));
+            }
+        }
+        None
+    }
+
+    /// Normalized position
+    fn pos(self, wave: &WaveDefinition) -> Option<Vec2> {
+        match self {
+            Self::Wall(i) => wave.walls.get(i).map(|v| v.pos),
+            Self::Turret(i) => wave.turrets.get(i).map(|v| v.pos),
+            Self::Boss => wave.boss.as_ref().map(|v| v.pos),
+        }
+    }
+
+    fn set_pos(self, wave: &mut WaveDefinition, pos: Vec2) {
+        match self {
+            Self::Wall(i) => wave.walls[i].pos = pos,
+            Self::Turret(i) => wave.turrets[i].pos = pos,
+            Self::Boss => wave.boss.get_or_insert_with(|| BossPlacement::new(pos)).pos = pos,
+        }
+    }
+
+    fn remove(self, wave: &mut WaveDefinition) {
+        match self {
+            Self::Wall(i) => {
+                wave.walls.remove(i);
+            }
+            Self::Turret(i) => {
+                wave.turrets.remove(i);
+            }
+            Self::Boss => wave.boss = None,
+        }
+    }
+
+    /// Outline shown by the editor marker
+    fn extents(self, wave: &WaveDefinition) -> Vec2 {
+        match self {
+            Self::Wall(i) => wave.walls[i].size,
+            Self::Turret(_) => Vec2::splat(1.2),
+            Self::Boss => Vec2::splat(4.),
+        }
+    }
+}
+
+/// Outline of the object being placed or moved
+#[derive(Component)]
+struct EditorMarker;
+
+//
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelEditor>()
+            .add_startup_system(spawn_marker)
+            .add_system(editor_state);
+        if !app.is_headless() {
+            app.add_system(editor_window)
+                .add_system(editor_input.after(editor_window));
+        }
+    }
+}
+
+fn spawn_marker(mut commands: Commands) {
+    use bevy_lyon::*;
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &shapes::Circle::default(),
+            DrawMode::Stroke(StrokeMode::new(Color::YELLOW, 0.1)),
+            default(),
+        ))
+        .insert(Visibility { is_visible: false })
+        .insert(Depth::WorldText)
+        .insert(EditorMarker);
+}
+
+fn editor_state(
+    mut editor: ResMut<LevelEditor>, spawn: Res<SpawnControl>, mut time_mode: ResMut<TimeMode>,
+    mut player: Query<&mut Health, With<Player>>,
+) {
+    // exited to main menu
+    if editor.active && spawn.despawn.is_none() && !spawn.is_game_running() {
+        editor.close()
+    }
+
+    time_mode.editor = editor.active && !editor.testing;
+    time_mode.editor_paused = editor.active && editor.paused;
+
+    if editor.active {
+        for mut health in player.iter_mut() {
+            health.invincible = true
+        }
+    }
+}
+
+fn editor_window(
+    mut ctx: ResMut<EguiContext>, mut editor: ResMut<LevelEditor>, mut spawn: ResMut<SpawnControl>,
+    time_mode: Res<TimeMode>,
+) {
+    if !editor.active || !spawn.is_game_running() || time_mode.main_menu {
+        return;
+    }
+    let editor = &mut *editor;
+    let mut respawn = false;
+
+    egui::Window::new("Level editor")
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(0., 0.))
+        .resizable(false)
+        .show(ctx.ctx_mut(), |ui| {
+            // waves
+            ui.horizontal(|ui| {
+                if ui.button("<").clicked() && editor.wave > 0 {
+                    editor.select_wave(editor.wave - 1);
+                    respawn = true;
+                }
+                ui.label(format!(
+                    "Wave {} / {}",
+                    editor.wave + 1,
+                    editor.list.waves.len()
+                ));
+                if ui.button(">").clicked() && editor.wave + 1 < editor.list.waves.len() {
+                    editor.select_wave(editor.wave + 1);
+                    respawn = true;
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Insert new").clicked() {
+                    editor.list.waves.insert(editor.wave + 1, default());
+                    editor.select_wave(editor.wave + 1);
+                    respawn = true;
+                }
+                if ui.button("Delete").clicked() && editor.list.waves.len() > 1 {
+                    editor.list.waves.remove(editor.wave);
+                    editor.select_wave(editor.wave);
+                    respawn = true;
+                }
+            });
+
+            ui.label(""); // separator
+            ui.label("Left click - place or select, right click - delete");
+            ui.radio_value(&mut editor.tool, EditorTool::Select, "Select & move");
+            ui.radio_value(&mut editor.tool, EditorTool::Wall, "Wall");
+            ui.radio_value(
+                &mut editor.tool,
+                EditorTool::Turret(TurretType::Simple),
+                "Turret (simple)",
+            );
+            ui.radio_value(
+                &mut editor.tool,
+                EditorTool::Turret(TurretType::Advanced),
+                "Turret (advanced)",
+            );
+            ui.radio_value(
+                &mut editor.tool,
+                EditorTool::Turret(TurretType::Rotating),
+                "Turret (rotating)",
+            );
+            ui.radio_value(
+                &mut editor.tool,
+                EditorTool::Turret(TurretType::Drone),
+                "Drone (mobile)",
+            );
+            ui.radio_value(&mut editor.tool, EditorTool::Boss, "Boss");
+
+            let size_edit = |ui: &mut egui::Ui, size: &mut Vec2| {
+                let mut changed = false;
+                ui.horizontal(|ui| {
+                    ui.label("Size");
+                    for value in [&mut size.x, &mut size.y] {
+                        let response = ui.add(
+                            egui::DragValue::new(value)
+                                .speed(0.05)
+                                .clamp_range(0.5..=30.),
+                        );
+                        changed |=
+                            response.drag_released() || (response.changed() && !response.dragged());
+                    }
+                });
+                changed
+            };
+            if editor.tool == EditorTool::Wall {
+                size_edit(ui, &mut editor.wall_size);
+            }
+
+            if let Some(object) = editor.selected {
+                ui.label(""); // separator
+                ui.heading("SELECTED");
+                let wave = &mut editor.list.waves[editor.wave];
+                match object {
+                    EditorObject::Wall(i) => {
+                        ui.label("Wall");
+                        respawn |= size_edit(ui, &mut wave.walls[i].size);
+                    }
+                    EditorObject::Turret(i) => {
+                        ui.label("Turret");
+                        let ty = &mut wave.turrets[i].ty;
+                        ui.horizontal(|ui| {
+                            respawn |= ui.radio_value(ty, TurretType::Simple, "Simple").changed();
+                            respawn |= ui
+                                .radio_value(ty, TurretType::Advanced, "Advanced")
+                                .changed();
+                            respawn |= ui
+                                .radio_value(ty, TurretType::Rotating, "Rotating")
+                                .changed();
+                            respawn |= ui.radio_value(ty, TurretType::Drone, "Drone").changed();
+                        });
+                    }
+                    EditorObject::Boss => {
+                        ui.label("Boss");
+                        if let Some(boss) = wave.boss.as_mut() {
+                            ui.horizontal(|ui| {
+                                ui.label("ID");
+                                respawn |= ui.text_edit_singleline(&mut boss.id).lost_focus();
+                            });
+                        }
+                    }
+                }
+                if ui.button("Delete object").clicked() {
+                    object.remove(wave);
+                    editor.selected = None;
+                    respawn = true;
+                }
+            }
+
+            ui.label(""); // separator
+            ui.checkbox(&mut editor.snap, "Snap to grid");
+            let mut preview = !editor.paused;
+            ui.checkbox(&mut preview, "Preview attacks");
+            editor.paused = !preview;
+            ui.checkbox(&mut editor.testing, "Control the player");
+
+            ui.label(""); // separator
+            ui.horizontal(|ui| {
+                ui.label("File");
+                ui.text_edit_singleline(&mut editor.file);
+            });
+            ui.label("Must end with \".waves.ron\"");
+            ui.horizontal(|ui| {
+                #[cfg(not(target_arch = "wasm32"))]
+                if ui.button("Save").clicked() {
+                    editor.save()
+                }
+                if ui.button("Copy to clipboard").clicked() {
+                    ui.output().copied_text = editor.to_ron();
+                }
+            });
+            ui.label("Exit editor from the menu");
+        });
+
+    if respawn {
+        spawn.despawn = Some(true);
+    }
+}
+
+fn editor_input(
+    mut editor: ResMut<LevelEditor>, mut spawn: ResMut<SpawnControl>, window: Res<WindowInfo>,
+    buttons: Res<Input<MouseButton>>, keys: Res<Input<KeyCode>>, mut ctx: ResMut<EguiContext>,
+    time_mode: Res<TimeMode>,
+    mut marker: Query<(&mut Transform, &mut bevy_lyon::Path, &mut Visibility), With<EditorMarker>>,
+) {
+    use bevy_lyon::*;
+
+    let (mut marker_pos, mut marker_path, mut marker_visibility) = match marker.get_single_mut() {
+        Ok(v) => v,
+        Err(_) => return,
+    };
+    marker_visibility.is_visible = false;
+
+    if !editor.active || editor.testing || !spawn.is_game_running() || time_mode.main_menu {
+        editor.drag = None;
+        return;
+    }
+    let editor = &mut *editor;
+
+    let arena = Arena::default();
+    let snap_enabled = editor.snap;
+    let snap = |pos: Vec2| {
+        let cell = Player::DASH_DISTANCE / 2.;
+        let pos = if snap_enabled {
+            arena.offset + ((pos - arena.offset) / cell).round() * cell
+        } else {
+            pos
+        };
+        pos.clamp(
+            arena.offset - arena.size / 2.,
+            arena.offset + arena.size / 2.,
+        )
+    };
+    // keep files readable
+    let to_normalized = |pos: Vec2| (arena.to_normalized(pos) * 1000.).round() / 1000.;
+
+    let over_ui = ctx.ctx_mut().is_pointer_over_area();
+    let cursor = window.cursor;
+    let mut respawn = false;
+
+    if let Some(grab) = editor.drag {
+        // moving selected object
+        let target = snap(cursor + grab);
+        if let Some(object) = editor.selected {
+            if buttons.just_released(MouseButton::Left) {
+                if object.pos(editor.current()) != Some(to_normalized(target)) {
+                    object.set_pos(editor.current(), to_normalized(target));
+                    respawn = true;
+                }
+                editor.drag = None;
+            }
+            marker_pos.set_2d(target);
+            *marker_path = ShapePath::build_as(&shapes::Rectangle {
+                extents: object.extents(editor.current()),
+                origin: RectangleOrigin::Center,
+            });
+            marker_visibility.is_visible = true;
+        } else {
+            editor.drag = None;
+        }
+    } else if !over_ui {
+        let target = snap(cursor);
+
+        if buttons.just_pressed(MouseButton::Left) {
+            let wave = &mut editor.list.waves[editor.wave];
+            let created = match editor.tool {
+                EditorTool::Select => {
+                    editor.selected = EditorObject::pick(wave, arena, cursor);
+                    if let Some(pos) = editor.selected.and_then(|object| object.pos(wave)) {
+                        editor.drag = Some(arena.to_world(pos) - cursor);
+                    }
+                    None
+                }
+                EditorTool::Wall => {
+                    wave.walls.push(WallDefinition {
+                        pos: to_normalized(target),
+                        size: editor.wall_size,
+                    });
+                    Some(EditorObject::Wall(wave.walls.len() - 1))
+                }
+                EditorTool::Turret(ty) => {
+                    wave.turrets.push(TurretDefinition {
+                        pos: to_normalized(target),
+                        ty,
+                        loot: None,
+                        movement: None,
+                    });
+                    Some(EditorObject::Turret(wave.turrets.len() - 1))
+                }
+                EditorTool::Boss => {
+                    wave.boss = Some(BossPlacement::new(to_normalized(target)));
+                    Some(EditorObject::Boss)
+                }
+            };
+            if created.is_some() {
+                editor.selected = created;
+                respawn = true;
+            }
+        } else if buttons.just_pressed(MouseButton::Right) {
+            if let Some(object) = EditorObject::pick(editor.current(), arena, cursor) {
+                object.remove(editor.current());
+                editor.selected = None;
+                respawn = true;
+            }
+        }
+
+        // placement preview
+        let extents = match editor.tool {
+            EditorTool::Select => editor
+                .selected
+                .map(|object| {
+                    (
+                        object.pos(editor.current()),
+                        object.extents(editor.current()),
+                    )
+                })
+                .and_then(|(pos, extents)| {
+                    marker_pos.set_2d(arena.to_world(pos?));
+                    Some(extents)
+                }),
+            EditorTool::Wall => Some(editor.wall_size),
+            EditorTool::Turret(_) => Some(Vec2::splat(1.2)),
+            EditorTool::Boss => Some(Vec2::splat(4.)),
+        };
+        if editor.tool != EditorTool::Select {
+            marker_pos.set_2d(target);
+        }
+        if let Some(extents) = extents {
+            *marker_path = ShapePath::build_as(&shapes::Rectangle {
+                extents,
+                origin: RectangleOrigin::Center,
+            });
+            marker_visibility.is_visible = true;
+        }
+    }
+
+    if keys.just_pressed(KeyCode::Delete) && !ctx.ctx_mut().wants_keyboard_input() {
+        if let Some(object) = editor.selected.take() {
+            object.remove(editor.current());
+            editor.drag = None;
+            respawn = true;
+        }
+    }
+
+    if respawn {
+        spawn.despawn = Some(true);
+    }
+}
@@ -70,7 +70,7 @@ fn draw_grid_pulse(
     color.set_next(pulse.color);
     let color = color.update(time.now());
 
-    *wave += time.delta_seconds() / pulse.period.as_secs_f32();
+    *wave += time.frame_delta_seconds() / pulse.period.as_secs_f32();
 
     for (bar, mut draw) in bars.iter_mut() {
         let t = match pulse.ty {
@@ -104,7 +104,7 @@ struct SelectState {
 fn select_grid_pulse(
     mut pulse: ResMut<GridPulse>, player: Query<&Health, With<Player>>,
     mut state: Local<SelectState>, mut wave_event: EventReader<WaveEvent>, beats: Res<Beats>,
-    real_time: Res<Time>,
+    time: Res<GameTime>,
 ) {
     for ev in wave_event.iter() {
         match ev {
@@ -116,14 +116,10 @@ fn select_grid_pulse(
 
     if state.wait_wave {
         *pulse = GridPulse::waiting()
-    } else if let Some(start) = beats
-        .start
-        .filter(|start| real_time.time_since_startup() >= *start)
-    {
+    } else if let Some(start) = beats.start.filter(|start| time.real_now() >= *start) {
         let adjust = 0.05;
-        let t = (((real_time.time_since_startup() - start).as_secs_f32() + adjust)
-            / beats.period.as_secs_f32())
-        .fract();
+        let t =
+            ((time.passed_real(start).as_secs_f32() + adjust) / beats.period.as_secs_f32()).fract();
         *pulse = GridPulse::beat(1. - t)
     } else if let Ok(health) = player.get_single() {
         if health.value < health.max / 2. {
@@ -0,0 +1,176 @@
+use super::{editor::LevelEditor, save::RunSave, spawn::SpawnControl, stats::Stats};
+use crate::{
+    common::*,
+    control::replay::Replay,
+    settings::Difficulty,
+    storage::{unix_time, Storage},
+};
+use bevy::app::AppExit;
+use serde::{Deserialize, Serialize};
+
+/// Result of a finished run
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub points: usize,
+    /// Index of the last wave reached
+    pub wave: usize,
+    pub seconds: u64,
+    pub restarts: usize,
+    pub difficulty: Difficulty,
+    /// Seconds since UNIX epoch
+    pub date: u64,
+}
+
+/// Resource - persistent high scores and run history
+#[derive(Default, Serialize, Deserialize)]
+pub struct RunHistory {
+    /// Sorted by points, descending
+    pub best: Vec<RunRecord>,
+    /// Most recent first
+    pub recent: Vec<RunRecord>,
+}
+
+impl RunHistory {
+    // cookie size is limited
+    const MAX_BEST: usize = 10;
+    const MAX_RECENT: usize = 5;
+
+    const STORAGE: Storage = Storage {
+        name: "run history",
+        file: "history.ron",
+        cookie: "blobfight-history",
+    };
+
+    pub fn add(&mut self, record: RunRecord) {
+        let index = self.best.partition_point(|run| run.points >= record.points);
+        self.best.insert(index, record.clone());
+        self.best.truncate(Self::MAX_BEST);
+
+        self.recent.insert(0, record);
+        self.recent.truncate(Self::MAX_RECENT);
+    }
+
+    pub fn save(&self) {
+        Self::STORAGE.save(&ron::ser::to_string(self).unwrap())
+    }
+
+    pub fn load() -> Option<Self> {
+        ron::from_str(&Self::STORAGE.load()?).ok()
+    }
+
+    pub fn menu(&self, ui: &mut egui::Ui) {
+        ui.label("Best");
+        Self::table(ui, "history::best", &self.best, true);
+        ui.label(""); // separator
+        ui.label("Recent");
+        Self::table(ui, "history::recent", &self.recent, false);
+    }
+
+    fn table(ui: &mut egui::Ui, id: &str, runs: &[RunRecord], ranked: bool) {
+        if runs.is_empty() {
+            ui.label("No runs yet");
+            return;
+        }
+        egui::Grid::new(id).striped(true).show(ui, |ui| {
+            for header in [
+                "#",
+                "Points",
+                "Wave",
+                "Time",
+                "Restarts",
+                "Difficulty",
+                "Date",
+            ] {
+                ui.label(header);
+            }
+            ui.end_row();
+
+            for (i, run) in runs.iter().enumerate() {
+                ui.label(if ranked { format!("{}", i + 1) } else { "".to_string() });
+                ui.label(format!("{}", run.points));
+                ui.label(format!("{}", run.wave + 1));
+                ui.label(format!("{}:{:02}", run.seconds / 60, run.seconds % 60));
+                ui.label(format!("{}", run.restarts));
+                ui.label(format!("{:?}", run.difficulty));
+                ui.label(format_date(run.date));
+                ui.end_row();
+            }
+        });
+    }
+}
+
+/// YYYY-MM-DD (UTC)
+fn format_date(unix_time: u64) -> String {
+    // Source: http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = (unix_time / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{}-{:02}-{:02}", year, month, day)
+}
+
+//
+
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        // simulation must not overwrite saved history
+        if app.is_headless() {
+            app.init_resource::<RunHistory>();
+            return;
+        }
+        app.insert_resource(RunHistory::load().unwrap_or_default())
+            .add_system_to_stage(CoreStage::Last, record_run.exclusive_system().at_end());
+    }
+}
+
+#[derive(Default)]
+enum RunState {
+    #[default]
+    Stopped,
+    /// Replay or level editor
+    Ignored,
+    Recording(Difficulty),
+}
+
+fn record_run(
+    mut history: ResMut<RunHistory>, stats: Res<Stats>, spawn: Res<SpawnControl>,
+    settings: Res<Settings>, replay: Res<Replay>, editor: Res<LevelEditor>, save: Res<RunSave>,
+    mut exit: EventReader<AppExit>, mut state: Local<RunState>,
+) {
+    let running = spawn.is_game_running() && exit.iter().next().is_none();
+
+    match *state {
+        RunState::Stopped if running => {
+            *state = if replay.is_playing() || editor.is_active() {
+                RunState::Ignored
+            } else {
+                RunState::Recording(settings.difficulty())
+            }
+        }
+        RunState::Recording(difficulty) if !running => {
+            *state = RunState::Stopped;
+            // it will be recorded when finished
+            if stats.time.is_zero() || save.is_suspended() {
+                return;
+            }
+            history.add(RunRecord {
+                points: stats.player.points,
+                wave: stats.wave,
+                seconds: stats.time.as_secs(),
+                restarts: stats.restarts,
+                difficulty,
+                date: unix_time(),
+            });
+            history.save();
+        }
+        RunState::Ignored if !running => *state = RunState::Stopped,
+        _ => (),
+    }
+}
@@ -1,7 +1,6 @@
-use super::stats::Stats;
+use super::{stats::Stats, weapon::WeaponModifier};
 use crate::{
     common::*,
-    control::input::InputAction,
     mechanics::{
         health::{DeathEvent, DieAfter, Health},
         movement::DropSpread,
@@ -9,11 +8,16 @@ use crate::{
     present::sound::Sound,
 };
 use enum_map::Enum;
+use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Copy)]
 pub enum Loot {
-    Health { value: f32 },
+    Health {
+        value: f32,
+    },
     CraftPart(CraftPart),
+    /// Applied to the first crafted weapon which can have it
+    Modifier(WeaponModifier),
 }
 
 /// If present, entity will drop that on death
@@ -28,7 +32,7 @@ pub struct LootPicker {
     pub radius: f32,
 }
 
-#[derive(Clone, Copy, Enum, Debug)]
+#[derive(Clone, Copy, Enum, Debug, Serialize, Deserialize)]
 pub enum CraftPart {
     Generator,
     Emitter,
@@ -37,17 +41,17 @@ pub enum CraftPart {
 }
 
 impl CraftPart {
-    pub fn random() -> Self {
+    pub fn random(rng: &mut impl rand::Rng) -> Self {
         [Self::Generator, Self::Emitter, Self::Laser, Self::Magnet]
             .into_iter()
-            .random()
+            .random(rng)
     }
-    pub fn description(&self) -> (InputAction, &'static str, usize) {
+    pub fn description(&self) -> &'static str {
         match self {
-            CraftPart::Generator => (InputAction::CraftSelect1, "Generator", 1),
-            CraftPart::Emitter => (InputAction::CraftSelect2, "Emitter", 1),
-            CraftPart::Laser => (InputAction::CraftSelect3, "Laser", 2),
-            CraftPart::Magnet => (InputAction::CraftSelect4, "Magnet", 2),
+            CraftPart::Generator => "Generator",
+            CraftPart::Emitter => "Emitter",
+            CraftPart::Laser => "Laser",
+            CraftPart::Magnet => "Magnet",
         }
     }
 }
@@ -58,8 +62,8 @@ pub struct LootPlugin;
 
 impl Plugin for LootPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_to_stage(CoreStage::PostUpdate, drop_loot)
-            .add_system(pick_loot.exclusive_system());
+        app.add_game_system(GameStage::PostUpdate, drop_loot)
+            .add_game_system(GameStage::Update, pick_loot.exclusive_system());
     }
 }
 
@@ -72,6 +76,7 @@ fn drop_loot(
             let (radius, color) = match loot {
                 Loot::Health { .. } => (0.3, Color::GREEN * 0.8),
                 Loot::CraftPart(_) => (0.4, Color::ORANGE_RED * 0.8),
+                Loot::Modifier(_) => (0.4, Color::CYAN * 0.8),
             };
             let lifetime = Duration::from_secs(8);
 
@@ -112,6 +117,21 @@ fn drop_loot(
                                 default(),
                             ));
                         }
+                        Loot::Modifier(_) => {
+                            parent.spawn_bundle(GeometryBuilder::build_as(
+                                &shapes::Polygon {
+                                    points: vec![
+                                        vec2(0., radius),
+                                        vec2(-radius, 0.),
+                                        vec2(0., -radius),
+                                        vec2(radius, 0.),
+                                    ],
+                                    closed: true,
+                                },
+                                DrawMode::Fill(FillMode::color(color)),
+                                default(),
+                            ));
+                        }
                     };
                 })
                 .insert(PickableLoot(*loot))
@@ -164,6 +184,23 @@ fn pick_loot(
                                 ..default()
                             });
                         }
+
+                        Loot::Modifier(modifier) => {
+                            let player = &mut stats.player;
+                            let applied = [&mut player.weapon0, &mut player.weapon1]
+                                .into_iter()
+                                .flatten()
+                                .any(|weapon| weapon.apply(modifier));
+                            if applied {
+                                commands.entity(entity).despawn_recursive();
+                                sounds.send(Sound {
+                                    sound: assets.ui_pickup.clone(),
+                                    position: Some(pos),
+                                    non_randomized: true,
+                                    ..default()
+                                });
+                            }
+                        }
                     }
                 }
                 false
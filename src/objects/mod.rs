@@ -1,12 +1,22 @@
 use crate::common::*;
 
+pub mod asteroid;
 pub mod boss;
+pub mod crafting;
+pub mod editor;
 pub mod grid;
+pub mod history;
 pub mod loot;
 pub mod player;
+pub mod procedural;
+pub mod save;
+pub mod shop;
 pub mod spawn;
 pub mod stats;
+pub mod tutorial;
+pub mod waves;
 pub mod weapon;
+pub mod weapon_def;
 
 pub struct ObjectsPlugin;
 
@@ -18,6 +28,14 @@ impl Plugin for ObjectsPlugin {
             .add_plugin(stats::StatsPlugin)
             .add_plugin(loot::LootPlugin)
             .add_plugin(grid::GridPlugin)
-            .add_plugin(boss::BossPlugin);
+            .add_plugin(boss::BossPlugin)
+            .add_plugin(asteroid::AsteroidPlugin)
+            .add_plugin(tutorial::TutorialPlugin)
+            .add_plugin(editor::EditorPlugin)
+            .add_plugin(history::HistoryPlugin)
+            .add_plugin(save::SavePlugin)
+            .add_plugin(crafting::CraftingPlugin)
+            .add_plugin(weapon_def::WeaponDefPlugin)
+            .add_plugin(shop::ShopPlugin);
     }
 }
@@ -1,24 +1,26 @@
 use super::{
-    loot::{CraftPart, LootPicker},
+    crafting::{Recipe, RecipeBook},
+    editor::LevelEditor,
+    loot::LootPicker,
     spawn::{SpawnControl, WaveEvent},
     stats::Stats,
-    weapon::{CraftedWeapon, Weapon},
+    tutorial::TutorialState,
+    weapon::{CraftedWeapon, Weapon, WeaponInstance, WeaponModifier},
 };
 use crate::{
     common::*,
     control::{
         input::{InputAction, InputMap},
-        menu::is_exit_menu,
         time::TimeMode,
     },
     mechanics::{
-        damage::{BonkToTeam, Team},
+        damage::{BonkToTeam, Punch, Team},
         health::{Health, ReceivedDamage},
         movement::*,
     },
     present::{
         camera::WindowInfo,
-        effect::{Flash, FlashOnDamage},
+        effect::{ChargingAttack, Flash, FlashOnDamage},
         hud_elements::WorldText,
         sound::{AudioListener, Beats, Sound},
     },
@@ -29,15 +31,20 @@ pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_to_stage(CoreStage::PreUpdate, spawn_player.exclusive_system())
-            .add_system(controls.before(MovementSystemLabel))
+        app.add_game_system(GameStage::PreUpdate, spawn_player.exclusive_system())
+            .add_game_system(GameStage::Update, controls.before(MovementSystemLabel))
+            .add_game_system(GameStage::Update, update_player)
+            .add_game_system(GameStage::Update, player_damage_reaction)
+            .add_game_system(GameStage::Update, next_wave.exclusive_system())
             .add_system(respawn)
-            .add_system(update_player)
-            .add_system(player_damage_reaction)
-            .add_system(next_wave.exclusive_system())
-            .add_system(hud_panel)
             .add_system(craft_menu)
-            .add_system(god_mode);
+            .add_system(god_mode)
+            .init_resource::<CraftMenu>();
+        if !app.is_headless() {
+            app.add_system(hud_panel)
+                .add_system(respawn_popup)
+                .add_system(craft_menu_window.before(craft_menu));
+        }
     }
 }
 
@@ -48,6 +55,8 @@ pub struct Player {
     prev_move: Vec2,
     beats_count: Option<i32>,
     fire_lock: Option<(Duration, bool)>,
+    /// (start, charging effect is shown)
+    charge_start: Option<(Duration, bool)>,
 }
 
 impl Player {
@@ -56,6 +65,11 @@ impl Player {
     pub const DASH_DISTANCE: f32 = 5.;
     const DASH_DURATION: Duration = Duration::from_millis(250);
     const SPEED: f32 = 7.;
+    const PUNCH_RADIUS: f32 = 1.5;
+    const PUNCH_EXHAUSTION: f32 = 0.5;
+    const CHARGE_DURATION: Duration = Duration::from_millis(1200);
+    /// Shorter holds fire only the normal shot, on press
+    const CHARGE_MIN: Duration = Duration::from_millis(300);
 
     fn exhaust(&mut self, value: f32) -> bool {
         if self.exhaustion + value <= Player::MAX_EXHAUSTION {
@@ -66,16 +80,17 @@ impl Player {
         }
     }
 
-    fn try_shoot(&mut self, time: &Time, mega: bool) -> bool {
+    fn can_shoot(&self, time: &GameTime, mega: bool) -> bool {
         let duration = Duration::from_millis(200);
-        let can_shoot = self
-            .fire_lock
-            .map(|(start, was_mega)| {
-                time.time_since_startup() - start >= duration || mega != was_mega
-            })
-            .unwrap_or(true);
+        self.fire_lock
+            .map(|(start, was_mega)| time.passed_real(start) >= duration || mega != was_mega)
+            .unwrap_or(true)
+    }
+
+    fn try_shoot(&mut self, time: &GameTime, mega: bool) -> bool {
+        let can_shoot = self.can_shoot(time, mega);
         if can_shoot {
-            self.fire_lock = Some((time.time_since_startup(), mega))
+            self.fire_lock = Some((time.real_now(), mega))
         }
         can_shoot
     }
@@ -88,6 +103,7 @@ impl Player {
 
 fn spawn_player(
     mut commands: Commands, player: Query<Entity, Added<Player>>, settings: Res<Settings>,
+    stats: Res<Stats>,
 ) {
     for entity in player.iter() {
         let radius = Player::RADIUS;
@@ -139,10 +155,12 @@ fn spawn_player(
             //
             .insert(Team::Player)
             .insert(
-                Health::new(match settings.difficulty {
-                    Difficulty::Easy => 8.,
-                    Difficulty::Hard => 3.,
-                })
+                Health::new(
+                    match settings.difficulty() {
+                        Difficulty::Easy => 8.,
+                        Difficulty::Hard => 3.,
+                    } + stats.player.max_health,
+                )
                 .armor(),
             )
             .insert(LootPicker {
@@ -163,7 +181,7 @@ fn controls(
     mut input: EventReader<InputAction>, mut kinematic: CmdWriter<KinematicCommand>,
     window: Res<WindowInfo>, time: Res<GameTime>, mut commands: Commands,
     mut weapon: CmdWriter<Weapon>, mut stats: ResMut<Stats>, mut beats: ResMut<Beats>,
-    mut time_mode: ResMut<TimeMode>, real_time: Res<Time>,
+    mut time_mode: ResMut<TimeMode>,
 ) {
     let (entity, pos, mut player, mut kctr) = match player.get_single_mut() {
         Ok(v) => v,
@@ -173,6 +191,9 @@ fn controls(
 
     let mut mov = Vec2::ZERO;
     let mut dash = false;
+    let mut punch = false;
+    let mut fire = false;
+    let mut charging = false;
     for action in input.iter() {
         match action {
             InputAction::MoveLeft => mov.x -= 1.,
@@ -181,9 +202,11 @@ fn controls(
             InputAction::MoveDown => mov.y -= 1.,
 
             InputAction::Dash => dash = true,
+            InputAction::Punch => punch = true,
 
             InputAction::Fire => {
-                if player.try_shoot(&real_time, false) {
+                fire = true;
+                if player.try_shoot(&time, false) {
                     weapon.send((
                         entity,
                         Weapon::PlayerGun {
@@ -192,8 +215,9 @@ fn controls(
                     ))
                 }
             }
+            InputAction::FireCharged => charging = true,
             InputAction::FireMega => {
-                if player.try_shoot(&real_time, true) {
+                if player.try_shoot(&time, true) {
                     weapon.send((
                         entity,
                         Weapon::PlayerCrafted {
@@ -245,34 +269,59 @@ fn controls(
             },
         ));
     }
+    // during dash it's applied along the whole trajectory
+    if punch && player.exhaust(Player::PUNCH_EXHAUSTION) {
+        commands.entity(entity).insert(Punch {
+            radius: Player::PUNCH_RADIUS,
+            target: window.cursor,
+            until: player.dash_until.unwrap_or_else(|| time.now()),
+            punched: vec![],
+        });
+    }
+
+    // normal shot is fired on press, charged one on release if it was held long enough.
+    // Click may be shorter than a tick, then it's released immediately.
+    if (fire || charging) && player.charge_start.is_none() {
+        player.charge_start = Some((time.now(), false));
+    }
+    if charging {
+        if let Some((start, shown)) = player.charge_start.as_mut() {
+            if !*shown && time.passed(*start) >= Player::CHARGE_MIN {
+                *shown = true;
+                commands.entity(entity).insert(ChargingAttack {
+                    radius: Player::RADIUS * 1.5,
+                    duration: Player::CHARGE_DURATION - Player::CHARGE_MIN,
+                    color: Color::rgb(1., 0.9, 0.5),
+                });
+            }
+        }
+    } else if let Some((start, shown)) = player.charge_start.take() {
+        if shown {
+            commands.entity(entity).remove::<ChargingAttack>();
+        }
+
+        if time.passed(start) >= Player::CHARGE_MIN {
+            let charge = time.t_passed(start, Player::CHARGE_DURATION).min(1.);
+            // nothing is spent if it can't be fired
+            if player.can_shoot(&time, false)
+                && player.exhaust(0.5 + charge)
+                && player.try_shoot(&time, false)
+            {
+                let dir = window.cursor - pos;
+                weapon.send((entity, Weapon::PlayerCharged { dir, charge }))
+            }
+        }
+    }
 }
 
 fn respawn(
-    mut ctx: ResMut<EguiContext>, player: Query<With<Player>>, mut spawn: ResMut<SpawnControl>,
-    mut input: EventReader<InputAction>, input_map: Res<InputMap>,
+    player: Query<With<Player>>, mut spawn: ResMut<SpawnControl>,
+    mut input: EventReader<InputAction>,
 ) {
     if !spawn.is_game_running() {
         return;
     }
     if player.is_empty() {
-        ctx.popup(
-            "player::respawn",
-            Vec2::ZERO,
-            true,
-            egui::Order::Background,
-            |ui| {
-                ui.heading("~= YOU DIED =~");
-
-                ui.horizontal(|ui| {
-                    // TODO: where should be a better way to make multicolored text
-                    ui.label("Press [");
-                    ui.visuals_mut().override_text_color = Some(egui::Color32::RED);
-                    ui.label(input_map.map[InputAction::Respawn].0.to_string());
-                    ui.visuals_mut().override_text_color = None;
-                    ui.label("] to restart level");
-                });
-            },
-        );
         for action in input.iter() {
             match action {
                 InputAction::Respawn => spawn.despawn = Some(true),
@@ -282,6 +331,33 @@ fn respawn(
     }
 }
 
+fn respawn_popup(
+    mut ctx: ResMut<EguiContext>, player: Query<With<Player>>, spawn: Res<SpawnControl>,
+    input_map: Res<InputMap>,
+) {
+    if !spawn.is_game_running() || !player.is_empty() {
+        return;
+    }
+    ctx.popup(
+        "player::respawn",
+        Vec2::ZERO,
+        true,
+        egui::Order::Background,
+        |ui| {
+            ui.heading("~= YOU DIED =~");
+
+            ui.horizontal(|ui| {
+                // TODO: where should be a better way to make multicolored text
+                ui.label("Press [");
+                ui.visuals_mut().override_text_color = Some(egui::Color32::RED);
+                ui.label(input_map.map[InputAction::Respawn].0.to_string());
+                ui.visuals_mut().override_text_color = None;
+                ui.label("] to restart level");
+            });
+        },
+    );
+}
+
 fn update_player(
     mut player: Query<(
         &mut Player,
@@ -291,6 +367,7 @@ fn update_player(
     )>,
     time: Res<GameTime>, mut beats: ResMut<Beats>, mut time_mode: ResMut<TimeMode>,
     mut stats: ResMut<Stats>, spawn: Res<SpawnControl>, window: Res<WindowInfo>,
+    tutorial: Res<TutorialState>,
 ) {
     let exhaust_restore_speed = 1.;
     let charge_time_seconds = 6.;
@@ -310,7 +387,7 @@ fn update_player(
             (player.exhaustion - time.delta_seconds() * exhaust_restore_speed).max(0.);
 
         // increase charge
-        if (!spawn.waiting_for_next_wave || spawn.tutorial.is_some()) && beats.level == 0 {
+        if (!spawn.waiting_for_next_wave || tutorial.is_active()) && beats.level == 0 {
             stats.ubercharge += time.delta_seconds() / charge_time_seconds;
         }
 
@@ -383,25 +460,26 @@ struct NextWaveMenu {
 fn next_wave(
     mut wave: EventReader<WaveEvent>, mut stats: ResMut<Stats>, mut spawn: ResMut<SpawnControl>,
     mut commands: Commands, mut input: EventReader<InputAction>, input_map: Res<InputMap>,
-    mut data: Local<NextWaveMenu>,
+    mut data: Local<NextWaveMenu>, tutorial: Res<TutorialState>, editor: Res<LevelEditor>,
 ) {
     let input_action = InputAction::Respawn;
 
+    // tutorial and editor handle waves by themselves
+    if tutorial.is_active() || editor.is_active() {
+        wave.clear();
+        if let Some(text) = data.text.take() {
+            commands.entity(text).despawn_recursive();
+        }
+        return;
+    }
+
     // begin user input
     if wave.iter().any(|ev| *ev == WaveEvent::Ended) {
-        let text = match spawn.tutorial {
-            Some(1) => vec![(
-                "This is tutorial\nRead text at the bottom of screen".to_string(),
-                Color::WHITE,
-            )],
-            // THE UGLY HACK. this is # of tutorial wave + 1
-            Some(4) | Some(7) | None => vec![
-                ("Press [".to_string(), Color::WHITE),
-                (input_map.map[input_action].0.to_string(), Color::RED),
-                ("] to go to next level".to_string(), Color::WHITE),
-            ],
-            Some(_) => vec![],
-        };
+        let text = vec![
+            ("Press [".to_string(), Color::WHITE),
+            (input_map.map[input_action].0.to_string(), Color::RED),
+            ("] to go to next level".to_string(), Color::WHITE),
+        ];
         if let Some(text) = data.text {
             commands.entity(text).despawn_recursive();
         }
@@ -417,9 +495,7 @@ fn next_wave(
         if spawn.is_game_running() {
             for input in input.iter() {
                 if *input == input_action {
-                    if spawn.tutorial.is_none() {
-                        stats.wave += 1;
-                    }
+                    stats.wave += 1;
                     spawn.despawn = Some(true);
 
                     commands.entity(text).despawn_recursive();
@@ -487,10 +563,13 @@ fn hud_panel(
                 ui.label("");
 
                 ui.label("WEAPONS");
-                for v in [stats.player.weapon0, stats.player.weapon1] {
-                    if let Some((weapon, uses)) = v {
-                        let (name, _, max_uses) = weapon.description();
-                        ui.label(format!("{} {}%", name, (uses / max_uses * 100.) as u32));
+                for v in [&stats.player.weapon0, &stats.player.weapon1] {
+                    if let Some(weapon) = v {
+                        ui.label(format!(
+                            "{} {}%",
+                            weapon.name(),
+                            (weapon.uses / weapon.max_uses() * 100.) as u32
+                        ));
                     } else {
                         ui.label("empty");
                     }
@@ -529,123 +608,73 @@ fn hud_panel(
     );
 }
 
+/// Resource - craft menu state
+#[derive(Default)]
 struct CraftMenu {
-    slot0: CraftPart,
-    slot1: CraftPart,
+    /// Index in the list of known recipes, followed by upgrades
+    selected: usize,
     show: bool,
 }
 
-impl Default for CraftMenu {
-    fn default() -> Self {
-        Self {
-            slot0: CraftPart::Emitter,
-            slot1: CraftPart::Laser,
-            show: false,
-        }
-    }
+/// Known recipes and upgrades which can be applied to the current weapon
+fn craft_items<'a>(
+    stats: &Stats, book: Option<&'a RecipeBook>,
+) -> (Vec<&'a Recipe>, Vec<WeaponModifier>) {
+    let known = book.map(|book| book.known(stats)).unwrap_or_default();
+    let upgrades = match &stats.player.weapon0 {
+        Some(weapon) => WeaponModifier::ALL
+            .into_iter()
+            .filter(|modifier| weapon.can_apply(*modifier))
+            .collect(),
+        None => vec![],
+    };
+    (known, upgrades)
 }
 
 fn craft_menu(
-    mut ctx: ResMut<EguiContext>, mut stats: ResMut<Stats>, input_map: Res<InputMap>,
-    mut input: EventReader<InputAction>, mut menu: Local<CraftMenu>, keys: Res<Input<KeyCode>>,
+    mut stats: ResMut<Stats>, mut input: EventReader<InputAction>, mut menu: ResMut<CraftMenu>,
     mut time_mode: ResMut<TimeMode>, player: Query<(), With<Player>>,
+    books: Res<Assets<RecipeBook>>, assets: Res<MyAssets>,
 ) {
     time_mode.craft_menu = menu.show;
     time_mode.player_alive = !player.is_empty();
 
     if menu.show {
-        if is_exit_menu(&keys) {
-            menu.show = false
-        }
-
-        let craft_result = match (menu.slot0, menu.slot1) {
-            (CraftPart::Generator, CraftPart::Laser) => Some(CraftedWeapon::Plasma),
-            // (CraftPart::Generator, CraftPart::Magnet) => Some(CraftedWeapon::Shield),
-            (CraftPart::Emitter, CraftPart::Laser) => Some(CraftedWeapon::Railgun),
-            // (CraftPart::Emitter, CraftPart::Magnet) => Some(CraftedWeapon::Repeller),
-            _ => {
-                log::error!("Invalid craft slots: {:?} and {:?}", menu.slot0, menu.slot1);
-                None
-            }
-        };
-
-        ctx.popup(
-            "player::craft_menu",
-            vec2(0., -1.),
-            true,
-            egui::Order::Background,
-            |ui| {
-                ui.label("Press [ESC] or [M] to close this menu"); // TODO: unhardcode
-                ui.group(|ui| {
-                    ui.label("Available parts");
-                    ui.group(|ui| {
-                        ui.label("Press 1/2/3/4 to put part in the slot.");
-                        ui.label("Currently there are only 4 combinations,");
-                        ui.label("so slot is chosen automatically.");
-                    });
-                    for (key, value) in stats.player.craft_parts.iter() {
-                        let (action, name, slot) = key.description();
-                        ui.visuals_mut().override_text_color = Some(if *value == 0 {
-                            egui::Color32::DARK_GRAY
-                        } else {
-                            egui::Color32::WHITE
-                        });
-                        ui.label(format!(
-                            "[slot {}: {}] {} x{}",
-                            slot,
-                            input_map.map[action].0.to_string(),
-                            name,
-                            *value
-                        ));
-                        ui.visuals_mut().override_text_color = None;
-                    }
-                });
-                ui.group(|ui| {
-                    ui.visuals_mut().override_text_color =
-                        Some(if stats.player.craft_parts[menu.slot0] == 0 {
-                            egui::Color32::DARK_GRAY
-                        } else {
-                            egui::Color32::WHITE
-                        });
-                    ui.label(format!("Slot 1: {}", menu.slot0.description().1));
-
-                    ui.visuals_mut().override_text_color =
-                        Some(if stats.player.craft_parts[menu.slot1] == 0 {
-                            egui::Color32::DARK_GRAY
-                        } else {
-                            egui::Color32::WHITE
-                        });
-                    ui.label(format!("Slot 2: {}", menu.slot1.description().1));
-                    ui.visuals_mut().override_text_color = None;
-
-                    if let Some(result) = craft_result {
-                        let (name, text, _) = result.description();
-                        ui.label(format!("Result: {}", name));
-                        ui.label(text);
-                    } else {
-                        ui.label("Combination not implemented");
-                    }
-                });
-                ui.label("Press [C] to craft new weapon (replaces current)");
-            },
-        );
+        let (known, upgrades) = craft_items(&stats, books.get(&assets.recipes));
+        let items = known.len() + upgrades.len();
+        menu.selected = menu.selected.min(items.saturating_sub(1));
 
         for action in input.iter() {
             match action {
-                InputAction::CraftSelect1 => menu.slot0 = CraftPart::Generator,
-                InputAction::CraftSelect2 => menu.slot0 = CraftPart::Emitter,
-                InputAction::CraftSelect3 => menu.slot1 = CraftPart::Laser,
-                InputAction::CraftSelect4 => menu.slot1 = CraftPart::Magnet,
+                InputAction::CraftPrevious => {
+                    menu.selected = menu
+                        .selected
+                        .checked_sub(1)
+                        .unwrap_or(items.saturating_sub(1))
+                }
+                InputAction::CraftNext => menu.selected = (menu.selected + 1) % items.max(1),
+                InputAction::CloseMenu => menu.show = false,
                 InputAction::Craft => {
-                    if let Some(weapon) = craft_result {
-                        if stats.player.craft_parts[menu.slot0] != 0
-                            && stats.player.craft_parts[menu.slot1] != 0
-                        {
-                            stats.player.craft_parts[menu.slot0] -= 1;
-                            stats.player.craft_parts[menu.slot1] -= 1;
+                    if let Some(recipe) = known.get(menu.selected) {
+                        if recipe.can_craft(&stats.player.craft_parts) {
+                            recipe.consume(&mut stats.player.craft_parts);
                             menu.show = false;
 
-                            stats.player.weapon0 = Some((weapon, weapon.description().2))
+                            stats.player.weapon0 = Some(
+                                WeaponInstance::new(recipe.result, recipe.uses())
+                                    .with_max_uses(recipe.uses()),
+                            )
+                        }
+                    } else if let Some(modifier) =
+                        upgrades.get(menu.selected.wrapping_sub(known.len()))
+                    {
+                        let price = modifier.description().2;
+                        if stats.player.points >= price {
+                            if let Some(weapon) = stats.player.weapon0.as_mut() {
+                                if weapon.apply(*modifier) {
+                                    stats.player.points -= price;
+                                }
+                            }
                         }
                     }
                 }
@@ -661,6 +690,101 @@ fn craft_menu(
     }
 }
 
+fn craft_menu_window(
+    mut ctx: ResMut<EguiContext>, stats: Res<Stats>, input_map: Res<InputMap>,
+    menu: Res<CraftMenu>, books: Res<Assets<RecipeBook>>, assets: Res<MyAssets>,
+) {
+    if !menu.show {
+        return;
+    }
+    let (known, upgrades) = craft_items(&stats, books.get(&assets.recipes));
+    let selected = menu
+        .selected
+        .min((known.len() + upgrades.len()).saturating_sub(1));
+
+    let color =
+        |enabled: bool| Some(if enabled { egui::Color32::WHITE } else { egui::Color32::DARK_GRAY });
+
+    ctx.popup(
+        "player::craft_menu",
+        vec2(0., -1.),
+        true,
+        egui::Order::Background,
+        |ui| {
+            ui.label(format!(
+                "Press [{}] to close this menu",
+                input_map.map[InputAction::CloseMenu].0.to_string()
+            ));
+            ui.group(|ui| {
+                ui.label("Available parts");
+                for (part, count) in stats.player.craft_parts.iter() {
+                    ui.visuals_mut().override_text_color = color(*count != 0);
+                    ui.label(format!("{} x{}", part.description(), *count));
+                    ui.visuals_mut().override_text_color = None;
+                }
+            });
+            ui.group(|ui| {
+                ui.label("Known recipes");
+                ui.label(format!(
+                    "Press [{}] and [{}] to select recipe or upgrade",
+                    input_map.map[InputAction::CraftPrevious].0.to_string(),
+                    input_map.map[InputAction::CraftNext].0.to_string()
+                ));
+                if known.is_empty() {
+                    ui.label("None");
+                }
+                for (i, recipe) in known.iter().enumerate() {
+                    ui.visuals_mut().override_text_color =
+                        color(recipe.can_craft(&stats.player.craft_parts));
+                    ui.label(format!(
+                        "{} {}: {}",
+                        if i == selected { ">" } else { " " },
+                        recipe.name,
+                        recipe.parts_description()
+                    ));
+                    ui.visuals_mut().override_text_color = None;
+                }
+            });
+            if let Some(weapon) = &stats.player.weapon0 {
+                ui.group(|ui| {
+                    ui.label(format!("Upgrades for {}", weapon.name()));
+                    if upgrades.is_empty() {
+                        ui.label("None");
+                    }
+                    for (i, modifier) in upgrades.iter().enumerate() {
+                        let (name, _, price) = modifier.description();
+                        ui.visuals_mut().override_text_color = color(stats.player.points >= price);
+                        ui.label(format!(
+                            "{} {}: {} points",
+                            if known.len() + i == selected { ">" } else { " " },
+                            name,
+                            price
+                        ));
+                        ui.visuals_mut().override_text_color = None;
+                    }
+                });
+            }
+            if let Some(recipe) = known.get(selected) {
+                ui.group(|ui| {
+                    let (name, text, _) = recipe.result.description();
+                    ui.label(format!("Result: {} ({} uses)", name, recipe.uses()));
+                    ui.label(text);
+                });
+                ui.label(format!(
+                    "Press [{}] to craft new weapon (replaces current)",
+                    input_map.map[InputAction::Craft].0.to_string()
+                ));
+            } else if let Some(modifier) = upgrades.get(selected.wrapping_sub(known.len())) {
+                ui.group(|ui| ui.label(modifier.description().1));
+                ui.label(format!(
+                    "Press [{}] to buy upgrade for current weapon",
+                    input_map.map[InputAction::Craft].0.to_string()
+                ));
+            }
+        },
+    );
+}
+
 fn god_mode(
     keys: Res<Input<KeyCode>>, mut player: Query<&mut Health, With<Player>>,
     mut stats: ResMut<Stats>,
@@ -669,6 +793,6 @@ fn god_mode(
         if let Ok(mut health) = player.get_single_mut() {
             health.invincible = true
         }
-        stats.player.weapon0 = Some((CraftedWeapon::Railgun, 100000.))
+        stats.player.weapon0 = Some(WeaponInstance::new(CraftedWeapon::Railgun, 100000.))
     }
 }
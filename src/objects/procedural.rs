@@ -0,0 +1,248 @@
+use super::{
+    player::Player,
+    spawn::TurretType,
+    waves::{
+        Arena, AsteroidDefinition, BossPlacement, TurretDefinition, WallDefinition, WaveDefinition,
+    },
+};
+use crate::common::*;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+/// Resource - settings of procedurally generated waves.
+/// They are used after the campaign ends, or from the start in endless mode.
+#[derive(Default)]
+pub struct WaveGenerator {
+    /// Skip the campaign
+    pub endless: bool,
+}
+
+impl WaveGenerator {
+    /// Every N-th wave has a boss
+    const BOSS_PERIOD: usize = 6;
+    const ASTEROIDS_MIN_WAVE: usize = 4;
+
+    /// Same wave number with same seed always gives the same result
+    pub fn generate(&self, seed: u64, wave: usize, arena: Arena) -> WaveDefinition {
+        let mut rng = StdRng::seed_from_u64(GameRng::mix(seed, wave as u64));
+        let mut grid = CellGrid::new(arena);
+
+        let boss = (wave + 1) % Self::BOSS_PERIOD == 0;
+        let mut budget = 4. + wave as f32 * 1.5;
+
+        // player spawns in the center
+        grid.reserve(arena.offset, CellGrid::CELL * 1.5);
+
+        let boss = boss.then(|| {
+            budget *= 0.5;
+            // alternate between the big boss and the mini-boss
+            if (wave + 1) / Self::BOSS_PERIOD % 2 == 0 {
+                let pos = vec2(arena.to_normalized(Vec2::ZERO).x, 0.5);
+                grid.reserve(arena.to_world(pos), 8.);
+                BossPlacement {
+                    pos,
+                    id: "shield_robot".to_string(),
+                }
+            } else {
+                let pos = vec2(arena.to_normalized(Vec2::ZERO).x, 1.);
+                grid.reserve(arena.to_world(pos), 6.);
+                BossPlacement::new(pos)
+            }
+        });
+
+        // walls
+        let count = rng.gen_range(2..=5) + (wave / 3).min(6);
+        let mut walls = vec![];
+        for _ in 0..count * 4 {
+            if walls.len() >= count {
+                break;
+            }
+            let size = IVec2::new(rng.gen_range(1..=2), rng.gen_range(1..=2));
+            let min = IVec2::new(
+                rng.gen_range(0..=grid.size.x - size.x),
+                rng.gen_range(0..=grid.size.y - size.y),
+            );
+            if grid.try_block(min, size) {
+                walls.push(WallDefinition {
+                    pos: arena.to_normalized(grid.center(min, size)),
+                    // same as campaign walls for single cell
+                    size: size.as_vec2() * CellGrid::CELL - 1.2,
+                });
+            }
+        }
+
+        // turrets are solid too, so they block cells like walls
+        let mut cells: Vec<_> = grid
+            .free_cells()
+            .filter(|cell| !grid.is_reserved(*cell))
+            .filter(|cell| {
+                grid.center(*cell, IVec2::ONE).distance(arena.offset) > Player::DASH_DISTANCE * 1.5
+            })
+            .collect();
+        cells.shuffle(&mut rng);
+
+        let mut turrets = vec![];
+        loop {
+            let types: Vec<_> = [
+                (TurretType::Simple, 2., 0),
+                (TurretType::Rotating, 3., 1),
+                (TurretType::Advanced, 4., 2),
+                (TurretType::Drone, 3., 3),
+            ]
+            .into_iter()
+            .filter(|(_, cost, min_wave)| *cost <= budget && wave >= *min_wave)
+            .collect();
+            let (ty, cost, _) = match types.choose(&mut rng) {
+                Some(v) => *v,
+                None => break,
+            };
+            let cell = match std::iter::from_fn(|| cells.pop())
+                .find(|cell| grid.try_block(*cell, IVec2::ONE))
+            {
+                Some(v) => v,
+                None => break,
+            };
+            budget -= cost;
+            turrets.push(TurretDefinition {
+                pos: arena.to_normalized(grid.center(cell, IVec2::ONE)),
+                ty,
+                loot: None,
+                movement: None,
+            });
+        }
+
+        // asteroids, in remaining free cells
+        let mut asteroids = vec![];
+        if wave >= Self::ASTEROIDS_MIN_WAVE && rng.gen_bool(0.4) {
+            for _ in 0..rng.gen_range(1..=2) {
+                let cell = match cells.pop() {
+                    Some(v) => v,
+                    None => break,
+                };
+                asteroids.push(AsteroidDefinition {
+                    pos: arena.to_normalized(grid.center(cell, IVec2::ONE)),
+                    velocity: Vec2::Y.rotated(rng.gen_range(0. ..TAU)) * rng.gen_range(1. ..2.5),
+                    size: rng.gen_range(1..=2),
+                    loot: None,
+                });
+            }
+        }
+
+        WaveDefinition {
+            walls,
+            turrets,
+            boss,
+            asteroids,
+            loot: default(),
+        }
+    }
+}
+
+/// Arena divided in cells of the background grid
+struct CellGrid {
+    arena: Arena,
+    size: IVec2,
+    blocked: Vec<bool>,
+    /// Can't be blocked or occupied by turrets and asteroids
+    reserved: Vec<bool>,
+}
+
+impl CellGrid {
+    const CELL: f32 = Player::DASH_DISTANCE / 2.;
+
+    fn new(arena: Arena) -> Self {
+        // even number, so grid lines are aligned with the background grid
+        let size = (arena.size / 2. / Self::CELL).floor().as_ivec2() * 2;
+        let count = (size.x * size.y) as usize;
+        Self {
+            arena,
+            size,
+            blocked: vec![false; count],
+            reserved: vec![false; count],
+        }
+    }
+
+    fn index(&self, cell: IVec2) -> Option<usize> {
+        (cell.cmpge(IVec2::ZERO).all() && cell.cmplt(self.size).all())
+            .then(|| (cell.x + cell.y * self.size.x) as usize)
+    }
+
+    /// World position of the center of the rectangle of cells
+    fn center(&self, min: IVec2, size: IVec2) -> Vec2 {
+        let center = min.as_vec2() + size.as_vec2() / 2. - self.size.as_vec2() / 2.;
+        self.arena.offset + center * Self::CELL
+    }
+
+    fn cells(&self) -> impl Iterator<Item = IVec2> {
+        let size = self.size;
+        (0..size.y).flat_map(move |y| (0..size.x).map(move |x| IVec2::new(x, y)))
+    }
+
+    fn free_cells(&self) -> impl Iterator<Item = IVec2> + '_ {
+        self.cells()
+            .filter(|cell| !self.blocked[self.index(*cell).unwrap()])
+    }
+
+    fn is_reserved(&self, cell: IVec2) -> bool {
+        self.index(cell).map_or(false, |index| self.reserved[index])
+    }
+
+    fn reserve(&mut self, pos: Vec2, radius: f32) {
+        for cell in self.cells() {
+            if self.center(cell, IVec2::ONE).distance(pos) < radius {
+                let index = self.index(cell).unwrap();
+                self.reserved[index] = true;
+            }
+        }
+    }
+
+    /// Blocks rectangle of cells if all free space remains reachable
+    fn try_block(&mut self, min: IVec2, size: IVec2) -> bool {
+        let indices: Vec<_> = (0..size.y)
+            .flat_map(|y| (0..size.x).map(move |x| min + IVec2::new(x, y)))
+            .filter_map(|cell| self.index(cell))
+            .collect();
+        if indices.len() != (size.x * size.y) as usize
+            || indices
+                .iter()
+                .any(|i| self.blocked[*i] || self.reserved[*i])
+        {
+            return false;
+        }
+
+        for i in &indices {
+            self.blocked[*i] = true
+        }
+        let connected = self.connected();
+        if !connected {
+            for i in &indices {
+                self.blocked[*i] = false
+            }
+        }
+        connected
+    }
+
+    /// Are all free cells reachable from each other
+    fn connected(&self) -> bool {
+        let start = match self.free_cells().next() {
+            Some(v) => v,
+            None => return true,
+        };
+        let mut visited = vec![false; self.blocked.len()];
+        let mut stack = vec![start];
+        visited[self.index(start).unwrap()] = true;
+        let mut count = 0;
+
+        while let Some(cell) = stack.pop() {
+            count += 1;
+            for dir in [IVec2::X, -IVec2::X, IVec2::Y, -IVec2::Y] {
+                if let Some(index) = self.index(cell + dir) {
+                    if !self.blocked[index] && !visited[index] {
+                        visited[index] = true;
+                        stack.push(cell + dir);
+                    }
+                }
+            }
+        }
+        count == self.free_cells().count()
+    }
+}
@@ -0,0 +1,206 @@
+use super::{
+    editor::LevelEditor,
+    procedural::WaveGenerator,
+    spawn::SpawnControl,
+    stats::{PersistentPlayer, Stats},
+    tutorial::TutorialState,
+};
+use crate::{common::*, control::replay::Replay, settings::Difficulty, storage::Storage};
+use bevy::ecs::system::SystemParam;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+/// Run state between the waves
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SavedRun {
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    pub endless: bool,
+    pub tutorial_step: Option<usize>,
+    /// Wave which will be spawned on resume
+    pub wave: usize,
+    pub time: Duration,
+    pub restarts: usize,
+    pub ubercharge: f32,
+    #[serde(default)]
+    pub known_recipes: Vec<String>,
+    pub player: PersistentPlayer,
+}
+
+impl SavedRun {
+    /// Applies run settings, must be called before the spawn
+    pub fn configure(
+        &self, settings: &mut Settings, tutorial: &mut TutorialState,
+        generator: &mut WaveGenerator, editor: &mut LevelEditor, rng: &mut GameRng,
+    ) {
+        settings.run_difficulty = Some(self.difficulty);
+        match self.tutorial_step {
+            Some(step) => tutorial.start_at(step),
+            None => tutorial.stop(),
+        }
+        generator.endless = self.endless;
+        editor.close();
+        rng.set_next_seed(self.seed);
+    }
+}
+
+/// Resource - saved run and its restoration
+#[derive(Default)]
+pub struct RunSave {
+    saved: Option<SavedRun>,
+    /// Applied on the next first spawn
+    pending: Option<SavedRun>,
+    /// Current run was started from this save
+    current: Option<SavedRun>,
+    /// Current run was stopped by saving it
+    suspended: bool,
+    request: Option<SaveRequest>,
+}
+
+#[derive(Clone, Copy)]
+enum SaveRequest {
+    Save,
+    Continue,
+}
+
+impl RunSave {
+    const STORAGE: Storage = Storage {
+        name: "saved run",
+        file: "run.save.ron",
+        cookie: "blobfight-run",
+    };
+
+    pub fn saved(&self) -> Option<&SavedRun> {
+        self.saved.as_ref()
+    }
+
+    /// Save current run and exit to menu. Possible only between waves.
+    pub fn save(&mut self) {
+        self.request = Some(SaveRequest::Save)
+    }
+
+    /// Start saved run, game must not be running
+    pub fn resume(&mut self) {
+        self.request = Some(SaveRequest::Continue)
+    }
+
+    /// Restore that state on the next first spawn
+    pub fn set_pending(&mut self, saved: Option<SavedRun>) {
+        self.pending = saved
+    }
+
+    pub fn current(&self) -> Option<&SavedRun> {
+        self.current.as_ref()
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    /// Must be called on first spawn of the run, after stats are reset
+    pub fn start_run(&mut self, stats: &mut Stats) {
+        self.suspended = false;
+        self.current = self.pending.take();
+        if let Some(saved) = &self.current {
+            stats.wave = saved.wave;
+            stats.time = saved.time;
+            stats.restarts = saved.restarts;
+            stats.ubercharge = saved.ubercharge;
+            stats.known_recipes = saved.known_recipes.clone();
+            stats.set_player(saved.player.clone());
+        }
+    }
+
+    fn load() -> Option<SavedRun> {
+        ron::from_str(&Self::STORAGE.load()?).ok()
+    }
+
+    fn store(saved: &SavedRun) {
+        Self::STORAGE.save(&ron::ser::to_string(saved).unwrap())
+    }
+}
+
+//
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        // simulation must not overwrite saved run
+        if app.is_headless() {
+            app.init_resource::<RunSave>();
+            return;
+        }
+        app.insert_resource(RunSave {
+            saved: RunSave::load(),
+            ..default()
+        })
+        .add_system(handle_request);
+    }
+}
+
+#[derive(SystemParam)]
+struct RunState<'w, 's> {
+    stats: Res<'w, Stats>,
+    settings: ResMut<'w, Settings>,
+    tutorial: ResMut<'w, TutorialState>,
+    generator: ResMut<'w, WaveGenerator>,
+    editor: ResMut<'w, LevelEditor>,
+    rng: ResMut<'w, GameRng>,
+    replay: Res<'w, Replay>,
+    #[system_param(ignore)]
+    _unused: PhantomData<&'s ()>,
+}
+
+fn handle_request(mut save: ResMut<RunSave>, mut spawn: ResMut<SpawnControl>, mut run: RunState) {
+    match save.request.take() {
+        Some(SaveRequest::Save) => {
+            if !spawn.is_game_running()
+                || !spawn.waiting_for_next_wave
+                || run.editor.is_active()
+                || run.replay.is_playing()
+            {
+                log::warn!("Run can't be saved now");
+                return;
+            }
+            // tutorial waves don't advance the counter
+            let next_wave = if run.tutorial.is_active() { 0 } else { 1 };
+            let saved = SavedRun {
+                seed: run.rng.seed(),
+                difficulty: run.settings.difficulty(),
+                endless: run.generator.endless,
+                tutorial_step: run.tutorial.next_step(),
+                wave: run.stats.wave + next_wave,
+                time: run.stats.time,
+                restarts: run.stats.restarts,
+                ubercharge: run.stats.ubercharge,
+                known_recipes: run.stats.known_recipes.clone(),
+                player: run.stats.player.clone(),
+            };
+            RunSave::store(&saved);
+            save.saved = Some(saved);
+            save.suspended = true;
+            spawn.despawn = Some(false);
+        }
+        Some(SaveRequest::Continue) => {
+            if spawn.is_game_running() {
+                return;
+            }
+            // save can be continued only once
+            if let Some(saved) = save.saved.take() {
+                RunSave::STORAGE.remove();
+                log::info!("Continuing saved run, wave {}", saved.wave + 1);
+                saved.configure(
+                    &mut run.settings,
+                    &mut run.tutorial,
+                    &mut run.generator,
+                    &mut run.editor,
+                    &mut run.rng,
+                );
+                save.pending = Some(saved);
+                spawn.despawn = Some(true);
+            }
+        }
+        None => (),
+    }
+}
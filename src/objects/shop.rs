@@ -0,0 +1,214 @@
+use super::{
+    editor::LevelEditor, loot::CraftPart, player::Player, spawn::SpawnControl, stats::Stats,
+    tutorial::TutorialState,
+};
+use crate::{
+    common::*,
+    control::{
+        input::{InputAction, InputMap},
+        time::TimeMode,
+    },
+    mechanics::health::Health,
+    present::sound::Sound,
+    settings::Difficulty,
+};
+use bevy::ecs::system::SystemParam;
+use std::marker::PhantomData;
+
+#[derive(Clone, Copy)]
+pub enum ShopItem {
+    CraftPart(CraftPart),
+    /// Restores all uses of the current crafted weapon
+    Repair,
+    /// Restores all health
+    Heal,
+    /// Permanently increases max health
+    MaxHealth,
+}
+
+impl ShopItem {
+    const ALL: [Self; 7] = [
+        Self::CraftPart(CraftPart::Generator),
+        Self::CraftPart(CraftPart::Emitter),
+        Self::CraftPart(CraftPart::Laser),
+        Self::CraftPart(CraftPart::Magnet),
+        Self::Repair,
+        Self::Heal,
+        Self::MaxHealth,
+    ];
+    const MAX_HEALTH_INCREASE: f32 = 1.;
+
+    // (name, base price)
+    fn description(&self) -> (&'static str, usize) {
+        match self {
+            ShopItem::CraftPart(part) => (part.description(), 60),
+            ShopItem::Repair => ("Repair weapon", 100),
+            ShopItem::Heal => ("Heal", 80),
+            ShopItem::MaxHealth => ("Max health +1", 250),
+        }
+    }
+
+    /// Prices increase with each wave
+    pub fn price(&self, wave: usize, difficulty: Difficulty) -> usize {
+        let difficulty = match difficulty {
+            Difficulty::Easy => 1.,
+            Difficulty::Hard => 1.5,
+        };
+        let wave = 1. + 0.2 * wave as f32;
+        (self.description().1 as f32 * wave * difficulty).round() as usize
+    }
+
+    /// Returns false if there is no point in buying it now
+    fn buy(&self, stats: &mut Stats, health: &mut Health) -> bool {
+        match self {
+            ShopItem::CraftPart(part) => stats.player.craft_parts[*part] += 1,
+            ShopItem::Repair => match stats.player.weapon0.as_mut() {
+                Some(weapon) if weapon.uses < weapon.max_uses() => weapon.uses = weapon.max_uses(),
+                _ => return false,
+            },
+            ShopItem::Heal => {
+                if health.value >= health.max {
+                    return false;
+                }
+                health.value = health.max
+            }
+            ShopItem::MaxHealth => {
+                stats.player.max_health += Self::MAX_HEALTH_INCREASE;
+                health.max += Self::MAX_HEALTH_INCREASE;
+                health.value += Self::MAX_HEALTH_INCREASE;
+            }
+        }
+        true
+    }
+}
+
+//
+
+pub struct ShopPlugin;
+
+impl Plugin for ShopPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShopMenu>().add_system(shop);
+        if !app.is_headless() {
+            app.add_system(shop_window.before(shop));
+        }
+    }
+}
+
+/// Resource - shop menu state
+#[derive(Default)]
+struct ShopMenu {
+    selected: usize,
+}
+
+#[derive(SystemParam)]
+struct ShopState<'w, 's> {
+    spawn: Res<'w, SpawnControl>,
+    tutorial: Res<'w, TutorialState>,
+    editor: Res<'w, LevelEditor>,
+    time_mode: Res<'w, TimeMode>,
+    settings: Res<'w, Settings>,
+    #[system_param(ignore)]
+    _unused: PhantomData<&'s ()>,
+}
+
+impl<'w, 's> ShopState<'w, 's> {
+    /// Shop is open between the waves
+    fn is_open(&self) -> bool {
+        self.spawn.is_game_running()
+            && self.spawn.waiting_for_next_wave
+            && !self.tutorial.is_active()
+            && !self.editor.is_active()
+    }
+}
+
+fn shop(
+    mut stats: ResMut<Stats>, mut input: EventReader<InputAction>, mut menu: ResMut<ShopMenu>,
+    state: ShopState, mut player: Query<&mut Health, With<Player>>,
+    mut sound_cmd: EventWriter<Sound>, assets: Res<MyAssets>,
+) {
+    let mut health = match player.get_single_mut() {
+        Ok(health) => health,
+        Err(_) => return,
+    };
+    if !state.is_open() {
+        menu.selected = 0;
+        return;
+    }
+    // craft menu uses the same keys
+    if state.time_mode.craft_menu {
+        return;
+    }
+
+    let items = ShopItem::ALL.len();
+    let (wave, difficulty) = (stats.wave, state.settings.difficulty());
+    let price = move |item: &ShopItem| item.price(wave, difficulty);
+
+    for action in input.iter() {
+        match action {
+            InputAction::CraftPrevious => {
+                menu.selected = menu.selected.checked_sub(1).unwrap_or(items - 1)
+            }
+            InputAction::CraftNext => menu.selected = (menu.selected + 1) % items,
+            InputAction::Buy => {
+                let item = ShopItem::ALL[menu.selected];
+                let price = price(&item);
+                if stats.player.points >= price && item.buy(&mut stats, &mut health) {
+                    stats.player.points -= price;
+                    sound_cmd.send(Sound {
+                        sound: assets.ui_pickup.clone(),
+                        non_randomized: true,
+                        ..default()
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn shop_window(
+    mut ctx: ResMut<EguiContext>, stats: Res<Stats>, input_map: Res<InputMap>, menu: Res<ShopMenu>,
+    state: ShopState, player: Query<(), With<Player>>,
+) {
+    if player.is_empty() || !state.is_open() || state.time_mode.craft_menu {
+        return;
+    }
+
+    let (wave, difficulty) = (stats.wave, state.settings.difficulty());
+    let price = move |item: &ShopItem| item.price(wave, difficulty);
+
+    ctx.popup(
+        "shop::shop",
+        vec2(0., -1.),
+        true,
+        egui::Order::Background,
+        |ui| {
+            ui.label("SHOP");
+            ui.label(format!("Points: {}", stats.player.points));
+            ui.label(format!(
+                "Press [{}] and [{}] to select item, [{}] to buy it",
+                input_map.map[InputAction::CraftPrevious].0.to_string(),
+                input_map.map[InputAction::CraftNext].0.to_string(),
+                input_map.map[InputAction::Buy].0.to_string()
+            ));
+            ui.group(|ui| {
+                for (i, item) in ShopItem::ALL.iter().enumerate() {
+                    let price = price(item);
+                    ui.visuals_mut().override_text_color = Some(if stats.player.points >= price {
+                        egui::Color32::WHITE
+                    } else {
+                        egui::Color32::DARK_GRAY
+                    });
+                    ui.label(format!(
+                        "{} {}: {} points",
+                        if i == menu.selected { ">" } else { " " },
+                        item.description().0,
+                        price
+                    ));
+                    ui.visuals_mut().override_text_color = None;
+                }
+            });
+        },
+    );
+}
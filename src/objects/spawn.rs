@@ -1,16 +1,26 @@
-use super::{player::Player, stats::Stats};
+use super::{player::Player, save::RunSave, stats::Stats};
 use crate::{
+    assets::AppRonAsset,
     common::*,
+    control::rng::RngStream,
     mechanics::{
         ai::*,
+        behaviour::Behaviour,
         damage::Team,
         health::{DieAfter, Health},
+        movement::KinematicController,
+        navigation::PathTo,
     },
     objects::{
+        asteroid::{create_asteroid, Asteroid},
         boss::TheBoss,
+        editor::LevelEditor,
         grid::GridBar,
-        loot::{CraftPart, DropsLoot, Loot},
+        loot::DropsLoot,
+        procedural::WaveGenerator,
         stats::DeathPoints,
+        tutorial::{TutorialScript, TutorialState},
+        waves::{Arena, LootTable, WaveDefinition, WaveList},
         weapon::Weapon,
     },
     present::{
@@ -19,7 +29,10 @@ use crate::{
     },
     settings::Difficulty,
 };
-use std::f32::consts::SQRT_2;
+use bevy::ecs::system::SystemParam;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, f32::consts::SQRT_2, marker::PhantomData};
 
 /// Object which must be despawned
 #[derive(Component)]
@@ -34,7 +47,6 @@ pub struct SpawnControl {
 
     /// Set this to Some(true) to respawn, to Some(false) to despawn
     pub despawn: Option<bool>,
-    pub tutorial: Option<usize>,
 }
 
 impl SpawnControl {
@@ -51,22 +63,33 @@ pub enum WaveEvent {
     Restart, // sent with Started
 }
 
+/// Despawned when the next wave starts
 #[derive(Component)]
 pub struct TemporaryWall;
 
+/// Event - spawn turrets evenly around the origin. They're part of the wave, but drop no loot.
+pub struct SummonEvent {
+    pub origin: Vec2,
+    pub ty: TurretType,
+    pub count: usize,
+    pub radius: f32,
+}
+
 //
 
 pub struct SpawnPlugin;
 
 impl Plugin for SpawnPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<SpawnControl>()
+        app.add_ron_asset::<WaveList>()
+            .init_resource::<SpawnControl>()
+            .init_resource::<WaveGenerator>()
             .init_resource::<WaveData>()
-            .init_resource::<TutorialText>()
-            .add_event::<WaveEvent>()
-            .add_system_to_stage(CoreStage::First, spawn.exclusive_system())
-            .add_system(wave_end_detect)
-            .add_system(draw_tutorial_text);
+            .add_game_event::<WaveEvent>()
+            .add_game_event::<SummonEvent>()
+            .add_game_system(GameStage::First, spawn.exclusive_system())
+            .add_game_system(GameStage::Update, wave_end_detect)
+            .add_game_system(GameStage::PostUpdate, summon);
     }
 }
 
@@ -75,13 +98,65 @@ struct WaveData {
     entities: Vec<Entity>,
 }
 
+/// Where wave definitions come from
+#[derive(SystemParam)]
+struct WaveSources<'w, 's> {
+    assets: Res<'w, MyAssets>,
+    waves: Res<'w, Assets<WaveList>>,
+    tutorial: Res<'w, TutorialState>,
+    scripts: Res<'w, Assets<TutorialScript>>,
+    editor: Res<'w, LevelEditor>,
+    generator: Res<'w, WaveGenerator>,
+    #[system_param(ignore)]
+    _marker: PhantomData<&'s ()>,
+}
+
+impl<'w, 's> WaveSources<'w, 's> {
+    fn loaded(&self) -> bool {
+        self.waves.get(&self.assets.waves).is_some()
+            && (!self.tutorial.is_active() || self.scripts.get(&self.assets.tutorial).is_some())
+    }
+
+    fn get(&self, wave: usize, arena: Arena, seed: u64) -> Option<Cow<WaveDefinition>> {
+        if let Some(wave) = self.editor.wave() {
+            return Some(Cow::Borrowed(wave));
+        }
+
+        if let Some(step) = self.tutorial.step() {
+            let wave = self
+                .scripts
+                .get(&self.assets.tutorial)
+                .and_then(|script| script.steps.get(step))
+                .map(|step| Cow::Borrowed(&step.wave));
+            if wave.is_none() {
+                log::error!("No tutorial step {}", step)
+            }
+            return wave;
+        }
+
+        // not tutorial, actual game
+        let campaign = &self.waves.get(&self.assets.waves)?.waves;
+        match campaign.get(wave).filter(|_| !self.generator.endless) {
+            Some(wave) => Some(Cow::Borrowed(wave)),
+            None => Some(Cow::Owned(self.generator.generate(seed, wave, arena))),
+        }
+    }
+}
+
 fn spawn(
     mut commands: Commands, mut control: ResMut<SpawnControl>,
     entities: Query<Entity, With<GameplayObject>>, mut camera: Query<&mut WorldCamera>,
     mut stats: ResMut<Stats>, mut wave_data: ResMut<WaveData>,
-    mut wave_event: EventWriter<WaveEvent>, settings: Res<Settings>,
-    mut tutorial_text: ResMut<TutorialText>, tmp_walls: Query<Entity, With<TemporaryWall>>,
+    mut wave_event: EventWriter<WaveEvent>, mut settings: ResMut<Settings>,
+    tmp_walls: Query<Entity, With<TemporaryWall>>, sources: WaveSources,
+    mut player: Query<&mut Transform, With<Player>>, mut rng: ResMut<GameRng>,
+    mut save: ResMut<RunSave>,
 ) {
+    // wait until waves are loaded
+    if control.despawn == Some(true) && !sources.loaded() {
+        return;
+    }
+
     if let Some(respawn) = control.despawn.take() {
         // despawn all objects only if it's despawn or respawn, but not next if it's next wave
         let despawn = !respawn || control.wave_spawned == Some(stats.wave);
@@ -97,6 +172,7 @@ fn spawn(
 
         if !respawn {
             control.wave_spawned = None;
+            settings.run_difficulty = None;
             return;
         }
 
@@ -104,6 +180,8 @@ fn spawn(
         let first_spawn = !control.is_game_running();
         if first_spawn {
             *stats = default();
+            rng.new_run();
+            save.start_run(&mut stats);
         }
 
         if control.wave_spawned == Some(stats.wave) {
@@ -118,12 +196,11 @@ fn spawn(
 
         use bevy_lyon::*;
 
-        let world_ratio = 16. / 9.;
-        let world_size = vec2(40., 40. / world_ratio);
-        camera.single_mut().target_size = world_size + 0.1;
-
-        let offset = vec2(1.8, 0.);
-        let world_size = world_size - offset.abs() * 2.;
+        let arena = Arena::default();
+        for mut camera in camera.iter_mut() {
+            camera.target_size = Arena::camera_size() + 0.1;
+        }
+        let (offset, world_size) = (arena.offset, arena.size);
 
         // only on first spawn or respawn
         if first_spawn || despawn {
@@ -217,277 +294,27 @@ fn spawn(
 
         // wave-specific spawns
 
-        match control.tutorial {
-            // IF YOU CHANGE ANYTHING HERE DON'T FORGET TO UPDATE HACK IN PLAYER NEXT WAVE MESSAGE!!!!
-            Some(0) => {
-                tutorial_text.0 = concat!(
-                    "This is a tutorial message!\n",
-                    "Press R key to show next message",
-                );
-            }
-            Some(1) => {
-                tutorial_text.0 = concat!(
-                    "Move around with W/A/S/D keys.\n",
-                    "Press SPACE key to dash in movement direction.\n",
-                    "You can change direction mid-dash, but can't stop.\n",
-                    "\n",
-                    "Dash gives temporary INVINCIBILITY, but consumes stamina.",
-                    "\n\nPress R key to show next message",
-                );
-            }
-            Some(2) => {
-                tutorial_text.0 = concat!(
-                    "Shoot with left mouse button.\n",
-                    "Shoot in the movement direction just after starting dash\n",
-                    "  to deal increased damage (ray will turn red).",
-                    "\n\nPress R key to start tutorial combat",
-                );
-            }
-            Some(3) => {
-                tutorial_text.0 = concat!("Destroy both turrets to finish the level!",);
-                wave_data.entities.push(create_turret(
-                    &mut commands,
-                    offset + vec2(world_size.x * -0.4, world_size.y * 0.1),
-                    settings.difficulty,
-                    TurretType::Simple,
-                ));
-                wave_data.entities.push(create_turret(
-                    &mut commands,
-                    offset + vec2(world_size.x * 0.4, world_size.y * -0.1),
-                    settings.difficulty,
-                    TurretType::Simple,
-                ));
-            }
-            Some(4) => {
-                tutorial_text.0 = concat!(
-                    "Sometimes enemies drop pieces which can be picked up:\n",
-                    "- green ones restore health;\n",
-                    "- red ones used to craft additional weapons.\n",
-                    "You can only have two such weapons (in addition to main one) and they have limited uses.\n",
-                    "Press C to access crafting menu.\n",
-                    "Shoot crafted weapon with right mouse button.\n",
-                    "Switch current weapon with F or mouse wheel.\n",
-                    "Try combining different attacks, like shooting plasma ball with railgun.",
-                    "\n\nPress R key to show next message",
-                );
-            }
-            Some(5) => {
-                tutorial_text.0 = concat!(
-                    "Over time you acquire focus charge.\n",
-                    "Destroy enemies and avoid damage to charge faster and stay focused longer.\n",
-                    "Press SHIFT at 100% charge to enter focus mode.\n",
-                    "Shoot in sync with the beat to GREATLY increase damage.",
-                    "\n\nPress R key to start tutorial combat (again)",
-                );
-            }
-            Some(6) => {
-                tutorial_text.0 = concat!("Try destroying the turret using focus mode!");
-                wave_data.entities.push(create_turret(
-                    &mut commands,
-                    offset + vec2(0., world_size.y * 0.35),
-                    settings.difficulty,
-                    TurretType::Simple,
-                ));
-            }
-            Some(_) => {
-                tutorial_text.0 = concat!(
-                    "That's it, end of tutorial!\n",
-                    "Game currently has no ending,\n",
-                    "Levels will be repeated after some time",
-                    "\n\nPress R key to PLAY\n",
-                    "And remember that you are damaged by your own explosions!",
-                );
-                control.tutorial = None;
-            }
-
-            // not tutorial, actual game
-            None => {
-                tutorial_text.0 = default();
-
-                match stats.wave % 6 {
-                    0 => {
-                        for pos in [
-                            vec2(world_size.x * -0.2, world_size.y * -0.3),
-                            vec2(world_size.x * -0.2, world_size.y * 0.3),
-                            //
-                            vec2(world_size.x * 0.2, world_size.y * -0.3),
-                            vec2(world_size.x * 0.2, world_size.y * 0.3),
-                        ] {
-                            create_wall(&mut commands, offset + pos, Vec2::splat(1.3))
-                        }
-                        wave_data.entities.push(create_turret(
-                            &mut commands,
-                            offset + vec2(-15., 0.),
-                            settings.difficulty,
-                            TurretType::Simple,
-                        ));
-                        wave_data.entities.push(create_turret(
-                            &mut commands,
-                            offset + vec2(15., 0.),
-                            settings.difficulty,
-                            TurretType::Simple,
-                        ));
-                    }
-                    1 => {
-                        wave_data.entities.push(create_turret(
-                            &mut commands,
-                            offset + vec2(-12., -7.),
-                            settings.difficulty,
-                            TurretType::Simple,
-                        ));
-                        wave_data.entities.push(create_turret(
-                            &mut commands,
-                            offset + vec2(-12., 7.),
-                            settings.difficulty,
-                            TurretType::Simple,
-                        ));
-                        wave_data.entities.push(create_turret(
-                            &mut commands,
-                            offset + vec2(12., -7.),
-                            settings.difficulty,
-                            TurretType::Simple,
-                        ));
-                        wave_data.entities.push(create_turret(
-                            &mut commands,
-                            offset + vec2(12., 7.),
-                            settings.difficulty,
-                            TurretType::Simple,
-                        ));
-                    }
-                    2 => {
-                        for pos in [vec2(0., 8.)] {
-                            create_wall(&mut commands, offset + pos, Vec2::splat(1.3))
-                        }
-                        wave_data.entities.push(create_turret(
-                            &mut commands,
-                            offset + vec2(-12., 7.),
-                            settings.difficulty,
-                            TurretType::Advanced,
-                        ));
-                        wave_data.entities.push(create_turret(
-                            &mut commands,
-                            offset + vec2(12., 7.),
-                            settings.difficulty,
-                            TurretType::Advanced,
-                        ));
-                        wave_data.entities.push(create_turret(
-                            &mut commands,
-                            offset + vec2(0., -1.),
-                            settings.difficulty,
-                            TurretType::Rotating,
-                        ));
-                    }
-                    3 => {
-                        for pos in [
-                            vec2(world_size.x * -0.1, world_size.y * -0.2),
-                            vec2(world_size.x * -0.1, world_size.y * 0.2),
-                            vec2(world_size.x * -0.1, world_size.y * -0.4),
-                            vec2(world_size.x * -0.1, world_size.y * 0.4),
-                            //
-                            vec2(world_size.x * 0.1, world_size.y * -0.2),
-                            vec2(world_size.x * 0.1, world_size.y * 0.2),
-                            vec2(world_size.x * 0.1, world_size.y * -0.4),
-                            vec2(world_size.x * 0.1, world_size.y * 0.4),
-                        ] {
-                            create_wall(&mut commands, offset + pos, Vec2::splat(1.3))
-                        }
-                        wave_data.entities.push(create_turret(
-                            &mut commands,
-                            offset + vec2(-10., 10.),
-                            settings.difficulty,
-                            TurretType::Simple,
-                        ));
-                        wave_data.entities.push(create_turret(
-                            &mut commands,
-                            offset + vec2(-10., -10.),
-                            settings.difficulty,
-                            TurretType::Simple,
-                        ));
-                        wave_data.entities.push(create_turret(
-                            &mut commands,
-                            offset + vec2(12., 8.),
-                            settings.difficulty,
-                            TurretType::Advanced,
-                        ));
-                        wave_data.entities.push(create_turret(
-                            &mut commands,
-                            offset + vec2(10., -3.),
-                            settings.difficulty,
-                            TurretType::Rotating,
-                        ));
-                    }
-                    4 => {
-                        for pos in [
-                            vec2(world_size.x * -0.36, -1.),
-                            vec2(world_size.x * -0.23, 1.),
-                            vec2(world_size.x * -0.1, 1.),
-                            vec2(world_size.x * 0.1, -1.),
-                            vec2(world_size.x * 0.23, 1.),
-                            vec2(world_size.x * 0.36, -1.),
-                        ] {
-                            create_wall(&mut commands, offset + pos, Vec2::splat(1.))
-                        }
-                        wave_data.entities.push(create_turret(
-                            &mut commands,
-                            offset + vec2(-15., 5.),
-                            settings.difficulty,
-                            TurretType::Advanced,
-                        ));
-                        wave_data.entities.push(create_turret(
-                            &mut commands,
-                            offset + vec2(-15., -5.),
-                            settings.difficulty,
-                            TurretType::Simple,
-                        ));
-                        wave_data.entities.push(create_turret(
-                            &mut commands,
-                            offset + vec2(15., 5.),
-                            settings.difficulty,
-                            TurretType::Advanced,
-                        ));
-                        wave_data.entities.push(create_turret(
-                            &mut commands,
-                            offset + vec2(15., -5.),
-                            settings.difficulty,
-                            TurretType::Simple,
-                        ));
-                    }
-                    _ => {
-                        for pos in [
-                            vec2(world_size.x * -0.1, world_size.y * -0.4),
-                            vec2(world_size.x * -0.1, world_size.y * -0.2),
-                            vec2(world_size.x * -0.1, world_size.y * 0.2),
-                            //
-                            vec2(world_size.x * 0.1, world_size.y * -0.4),
-                            vec2(world_size.x * 0.1, world_size.y * -0.2),
-                            vec2(world_size.x * 0.1, world_size.y * 0.2),
-                        ] {
-                            create_wall(&mut commands, offset + pos, Vec2::splat(1.3))
-                        }
-                        wave_data.entities.push(create_turret(
-                            &mut commands,
-                            offset + vec2(-10., 3.),
-                            settings.difficulty,
-                            TurretType::Rotating,
-                        ));
-                        wave_data.entities.push(create_turret(
-                            &mut commands,
-                            offset + vec2(10., -3.),
-                            settings.difficulty,
-                            TurretType::Advanced,
-                        ));
-                        wave_data.entities.push(
-                            commands
-                                .spawn_bundle(SpatialBundle::from_transform(Transform::new_2d(
-                                    vec2(0., world_size.y / 2.),
-                                )))
-                                .insert(TheBoss { world_size, offset })
-                                .insert(GameplayObject)
-                                .id(),
-                        );
+        if let Some(wave) = sources.get(stats.wave, arena, rng.seed()) {
+            // player isn't respawned on the next wave, so it may end up inside a new wall
+            if !despawn {
+                for mut transform in player.iter_mut() {
+                    let pos = transform.pos_2d();
+                    if wave.walls.iter().any(|wall| {
+                        let delta = (arena.to_world(wall.pos) - pos).abs();
+                        delta.cmplt(wall.size / 2. + Player::RADIUS).all()
+                    }) {
+                        transform.set_2d(arena.offset)
                     }
                 }
             }
+            spawn_wave(
+                &mut commands,
+                &wave,
+                arena,
+                settings.difficulty(),
+                &mut wave_data,
+                rng.gameplay(RngStream::Spawn),
+            );
         }
     }
 }
@@ -502,16 +329,102 @@ fn wave_end_detect(
         if wave_data.entities.is_empty() && !was_empty {
             control.waiting_for_next_wave = true;
             event.send(WaveEvent::Ended);
+        }
+    }
+}
 
-            if let Some(wave) = control.tutorial.as_mut() {
-                *wave += 1
-            }
+fn summon(
+    mut commands: Commands, mut events: EventReader<SummonEvent>, mut wave_data: ResMut<WaveData>,
+    settings: Res<Settings>, mut rng: ResMut<GameRng>,
+) {
+    let loot = LootTable {
+        chance_easy: 0.,
+        chance_hard: 0.,
+        ..default()
+    };
+    // keep them inside the arena
+    let arena = Arena::default();
+    let margin = 1.;
+    let (min, max) = (
+        arena.offset - arena.size / 2. + margin,
+        arena.offset + arena.size / 2. - margin,
+    );
+
+    for event in events.iter() {
+        let rng = rng.gameplay(RngStream::Spawn);
+        let angle = rng.gen_range(0. ..TAU);
+        for i in 0..event.count {
+            let angle = angle + TAU * i as f32 / event.count as f32;
+            wave_data.entities.push(create_turret(
+                &mut commands,
+                (event.origin + Vec2::Y.rotated(angle) * event.radius).clamp(min, max),
+                settings.difficulty(),
+                event.ty,
+                &loot,
+                MoveBehaviour::default(),
+                rng,
+            ));
         }
     }
 }
 
 //
 
+/// Spawns everything wave-specific
+fn spawn_wave(
+    commands: &mut Commands, wave: &WaveDefinition, arena: Arena, difficulty: Difficulty,
+    wave_data: &mut WaveData, rng: &mut impl Rng,
+) {
+    for wall in &wave.walls {
+        create_wall(commands, arena.to_world(wall.pos), wall.size)
+    }
+    for turret in &wave.turrets {
+        let movement = match turret.movement.clone().unwrap_or_default() {
+            MoveBehaviour::Patrol { points } => MoveBehaviour::Patrol {
+                points: points.into_iter().map(|pos| arena.to_world(pos)).collect(),
+            },
+            movement => movement,
+        };
+        wave_data.entities.push(create_turret(
+            commands,
+            arena.to_world(turret.pos),
+            difficulty,
+            turret.ty,
+            turret.loot.as_ref().unwrap_or(&wave.loot),
+            movement,
+            rng,
+        ));
+    }
+    for asteroid in &wave.asteroids {
+        create_asteroid(
+            commands,
+            arena.to_world(asteroid.pos),
+            asteroid.velocity,
+            Asteroid {
+                size: asteroid.size,
+                loot: asteroid.loot.clone(),
+            },
+            difficulty,
+            rng,
+        );
+    }
+    if let Some(boss) = &wave.boss {
+        wave_data.entities.push(
+            commands
+                .spawn_bundle(SpatialBundle::from_transform(Transform::new_2d(
+                    arena.to_world(boss.pos),
+                )))
+                .insert(TheBoss {
+                    id: boss.id.clone(),
+                    world_size: arena.size,
+                    offset: arena.offset,
+                })
+                .insert(GameplayObject)
+                .id(),
+        );
+    }
+}
+
 fn create_wall(commands: &mut Commands, origin: Vec2, extents: Vec2) {
     use bevy_lyon::*;
     commands
@@ -538,15 +451,18 @@ fn create_wall(commands: &mut Commands, origin: Vec2, extents: Vec2) {
         .insert(TemporaryWall);
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum TurretType {
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TurretType {
     Simple,
     Advanced,
     Rotating,
+    /// Mobile
+    Drone,
 }
 
 fn create_turret(
     commands: &mut Commands, origin: Vec2, difficulty: Difficulty, ty: TurretType,
+    loot: &LootTable, movement: MoveBehaviour, rng: &mut impl Rng,
 ) -> Entity {
     use bevy_lyon::*;
 
@@ -573,18 +489,50 @@ fn create_turret(
                 fill_mode: FillMode::color(Color::CRIMSON),
                 outline_mode: StrokeMode::new(Color::BEIGE, 0.05),
             },
+            TurretType::Drone => DrawMode::Outlined {
+                fill_mode: FillMode::color(Color::PURPLE),
+                outline_mode: StrokeMode::new(Color::PINK, 0.05),
+            },
         },
         Transform::new_2d(origin),
     ));
+    // only used when leading the target
+    let accuracy = match (ty, difficulty) {
+        (TurretType::Advanced, Difficulty::Easy) => 0.7,
+        (TurretType::Advanced, Difficulty::Hard) => 1.,
+        (_, Difficulty::Easy) => 0.5,
+        (_, Difficulty::Hard) => 0.8,
+    };
     match ty {
         TurretType::Simple | TurretType::Advanced => {
             commands.insert(LosCheck::default()).insert(FaceTarget {
                 rotation_speed: TAU * 0.4,
+                accuracy,
                 ..default()
             });
         }
         TurretType::Rotating => {
-            commands.insert(HeSpinsHeRotats::new(TAU * 0.33));
+            commands.insert(HeSpinsHeRotats::new(TAU * 0.33, rng));
+        }
+        TurretType::Drone => {
+            commands
+                .insert(LosCheck::default())
+                .insert(FaceTarget {
+                    rotation_speed: TAU * 0.6,
+                    accuracy,
+                    ..default()
+                })
+                .insert(MovementPattern {
+                    behaviour: movement,
+                    clockwise: rng.gen(),
+                    ..default()
+                })
+                .insert(KinematicController {
+                    speed: 3.,
+                    radius,
+                    ..default()
+                })
+                .insert(PathTo::default());
         }
     }
     commands
@@ -595,38 +543,17 @@ fn create_turret(
         .insert(GameplayObject)
         .insert(Target::Player)
         .insert(Team::Enemy)
-        .insert(match ty {
-            TurretType::Simple => AttackPattern::default()
-                .stage(1, Duration::from_secs(1), AttackStage::Wait)
-                .stage(
-                    5,
-                    Duration::from_millis(300),
-                    AttackStage::Shoot(vec![Weapon::Turret]),
-                )
-                .stage(1, Duration::from_secs(1), AttackStage::Wait),
-
-            TurretType::Advanced => AttackPattern::default()
-                .stage(1, Duration::from_millis(500), AttackStage::Wait)
-                .stage(
-                    4,
-                    Duration::from_millis(250),
-                    AttackStage::Shoot(vec![
-                        Weapon::AdvancedTurret { offset: 0. },
-                        Weapon::AdvancedTurret { offset: -1.25 },
-                        Weapon::AdvancedTurret { offset: 1.25 },
-                    ]),
-                ),
-
-            TurretType::Rotating => AttackPattern::default().stage(
-                1,
-                Duration::from_millis(150),
-                AttackStage::Shoot(vec![Weapon::RotatingTurret]),
-            ),
-        })
+        .insert(Behaviour::new(match ty {
+            TurretType::Simple => "simple_turret",
+            TurretType::Advanced => "advanced_turret",
+            TurretType::Rotating => "rotating_turret",
+            TurretType::Drone => "drone",
+        }))
         .insert(Health::new(match ty {
             TurretType::Simple => 3.,
             TurretType::Advanced => 6.,
             TurretType::Rotating => 10.,
+            TurretType::Drone => 4.,
         }))
         .insert(match ty {
             TurretType::Simple => DeathPoints {
@@ -641,53 +568,19 @@ fn create_turret(
                 value: 60,
                 charge: 0.4,
             },
+            TurretType::Drone => DeathPoints {
+                value: 50,
+                charge: 0.3,
+            },
         })
         //
-        .insert(RigidBody::Fixed)
+        .insert(match ty {
+            TurretType::Drone => RigidBody::KinematicPositionBased,
+            _ => RigidBody::Fixed,
+        })
         .insert(PhysicsType::Solid.rapier())
         .insert(Collider::ball(radius))
-        .insert(DropsLoot({
-            use rand::*;
-            let mut loot = vec![];
-            if match difficulty {
-                Difficulty::Easy => true,
-                Difficulty::Hard => thread_rng().gen_bool(0.8),
-            } {
-                if thread_rng().gen_bool(0.66) {
-                    loot.push(Loot::Health {
-                        value: match difficulty {
-                            Difficulty::Easy => 4.,
-                            Difficulty::Hard => 1.5,
-                        },
-                    });
-                }
-                if thread_rng().gen_bool(0.33) {
-                    loot.push(Loot::CraftPart(CraftPart::random()));
-                }
-            }
-            loot
-        }));
+        .insert(DropsLoot(loot.roll(difficulty, rng)));
 
     commands.id()
 }
-
-#[derive(Default)]
-struct TutorialText(&'static str);
-
-fn draw_tutorial_text(mut ctx: ResMut<EguiContext>, text: Res<TutorialText>) {
-    ctx.popup(
-        "draw_tutorial_text",
-        vec2(0., 1.),
-        false,
-        egui::Order::Background,
-        |ui| {
-            let t = 1.;
-            ui.visuals_mut().override_text_color = Some(egui::Color32::from_rgb(
-                lerp(128., 255., t) as u8,
-                lerp(128., 255., t) as u8,
-                lerp(128., 255., t) as u8,
-            ));
-            ui.heading(text.0);
-        },
-    );
-}
@@ -1,6 +1,11 @@
-use super::{loot::CraftPart, spawn::WaveEvent, weapon::CraftedWeapon};
+use super::{
+    loot::CraftPart,
+    spawn::WaveEvent,
+    weapon::{CraftedWeapon, WeaponInstance},
+};
 use crate::{common::*, mechanics::health::DeathEvent};
 use enum_map::EnumMap;
+use serde::{Deserialize, Serialize};
 
 #[derive(Default)]
 pub struct Stats {
@@ -8,18 +13,23 @@ pub struct Stats {
     pub time: Duration,
     pub restarts: usize,
     pub ubercharge: f32,
+    /// Names of discovered crafting recipes
+    pub known_recipes: Vec<String>,
 
     pub player: PersistentPlayer,
     last_wave: PersistentPlayer,
 }
 
 /// Stuff restored after respawn
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct PersistentPlayer {
     pub points: usize,
     pub craft_parts: EnumMap<CraftPart, usize>, // count
-    pub weapon0: Option<(CraftedWeapon, f32)>,  // usage left
-    pub weapon1: Option<(CraftedWeapon, f32)>,
+    pub weapon0: Option<WeaponInstance>,
+    pub weapon1: Option<WeaponInstance>,
+    /// Bought in shop
+    #[serde(default)]
+    pub max_health: f32,
 }
 
 impl Default for PersistentPlayer {
@@ -32,12 +42,21 @@ impl Default for PersistentPlayer {
                 CraftPart::Laser => 2,
                 CraftPart::Magnet => 2,
             },
-            weapon0: Some((CraftedWeapon::Railgun, 3.)),
-            weapon1: Some((CraftedWeapon::Plasma, 3.)),
+            weapon0: Some(WeaponInstance::new(CraftedWeapon::Railgun, 3.)),
+            weapon1: Some(WeaponInstance::new(CraftedWeapon::Plasma, 3.)),
+            max_health: 0.,
         }
     }
 }
 
+impl Stats {
+    /// Also used as the state restored after respawn
+    pub fn set_player(&mut self, player: PersistentPlayer) {
+        self.last_wave = player.clone();
+        self.player = player;
+    }
+}
+
 #[derive(Component, Default)]
 pub struct DeathPoints {
     pub value: usize,
@@ -51,7 +70,7 @@ pub struct StatsPlugin;
 impl Plugin for StatsPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<Stats>()
-            .add_system_to_stage(CoreStage::PostUpdate, update_stats);
+            .add_game_system(GameStage::PostUpdate, update_stats);
     }
 }
 
@@ -61,11 +80,12 @@ fn update_stats(
 ) {
     for ev in events.iter() {
         match ev {
-            WaveEvent::Started => *wave_now = true,
-            WaveEvent::Ended => {
+            WaveEvent::Started => {
+                // includes everything bought in shop between the waves
                 stats.last_wave = stats.player.clone();
-                *wave_now = false;
+                *wave_now = true
             }
+            WaveEvent::Ended => *wave_now = false,
             WaveEvent::Restart => {
                 stats.player = stats.last_wave.clone();
                 stats.restarts += 1;
@@ -0,0 +1,242 @@
+use super::{
+    player::Player,
+    spawn::{SpawnControl, WaveEvent},
+    waves::WaveDefinition,
+};
+use crate::{
+    assets::{AppRonAsset, RonAsset},
+    common::*,
+    control::input::{InputAction, InputMap},
+    mechanics::movement::KinematicCommand,
+    present::hud_elements::WorldText,
+};
+use bevy::{
+    asset::{AssetPath, LoadContext},
+    reflect::TypeUuid,
+};
+use serde::Deserialize;
+
+/// Asset - tutorial steps, in order
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "138ade93-54a8-49b7-8b47-a325e3ded752"]
+pub struct TutorialScript {
+    pub steps: Vec<TutorialStep>,
+}
+
+impl RonAsset for TutorialScript {
+    const EXTENSIONS: &'static [&'static str] = &["tutorial.ron"];
+
+    fn resolve(&mut self, load_context: &mut LoadContext) -> Vec<AssetPath<'static>> {
+        for (index, step) in self.steps.iter_mut().enumerate() {
+            step.wave
+                .validate(&format!("{:?}, step {}", load_context.path(), index + 1))
+        }
+        vec![]
+    }
+}
+
+/// Each step is spawned as a separate wave
+#[derive(Deserialize)]
+pub struct TutorialStep {
+    /// Shown on the screen during the whole step
+    pub text: String,
+    /// Shown in the world during the whole step
+    #[serde(default)]
+    pub hint: Option<String>,
+    #[serde(default)]
+    pub wave: WaveDefinition,
+    pub until: TutorialCondition,
+    /// If set, player has to press respawn key after condition is met
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+pub enum TutorialCondition {
+    PressKey(InputAction),
+    /// Dash specified number of times
+    Dash(usize),
+    /// Wave is ended
+    KillAll,
+}
+
+/// Resource
+#[derive(Default)]
+pub struct TutorialState {
+    step: Option<usize>,
+    /// Count for the condition
+    progress: usize,
+    /// Condition is met, waiting for confirmation
+    completed: bool,
+}
+
+impl TutorialState {
+    pub fn start(&mut self) {
+        *self = Self {
+            step: Some(0),
+            ..default()
+        }
+    }
+
+    pub fn start_at(&mut self, step: usize) {
+        *self = Self {
+            step: Some(step),
+            ..default()
+        }
+    }
+
+    pub fn stop(&mut self) {
+        *self = default()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.step.is_some()
+    }
+
+    /// Index of the current step
+    pub fn step(&self) -> Option<usize> {
+        self.step
+    }
+
+    /// Step which will be spawned after respawn
+    pub fn next_step(&self) -> Option<usize> {
+        self.step
+            .map(|step| if self.completed { step + 1 } else { step })
+    }
+}
+
+//
+
+pub struct TutorialPlugin;
+
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_ron_asset::<TutorialScript>()
+            .init_resource::<TutorialState>()
+            .add_game_system(GameStage::Update, tutorial);
+        if !app.is_headless() {
+            app.add_system(draw_tutorial_text);
+        }
+    }
+}
+
+fn tutorial(
+    mut state: ResMut<TutorialState>, mut spawn: ResMut<SpawnControl>,
+    scripts: Res<Assets<TutorialScript>>, assets: Res<MyAssets>,
+    mut input: EventReader<InputAction>, mut wave: EventReader<WaveEvent>,
+    mut kinematic: CmdReader<KinematicCommand>, player: Query<(), With<Player>>,
+    input_map: Res<InputMap>, mut commands: Commands, mut text: Local<Option<Entity>>,
+) {
+    let mut set_text = |commands: &mut Commands, value: Option<String>| {
+        if let Some(entity) = text.take() {
+            commands.entity(entity).despawn_recursive()
+        }
+        *text = value.map(|value| {
+            commands
+                .spawn_bundle(SpatialBundle::default())
+                .insert(WorldText {
+                    text: vec![(value, Color::WHITE)],
+                    size: 2.,
+                })
+                .id()
+        });
+    };
+
+    let step = match state
+        .step
+        .zip(scripts.get(&assets.tutorial))
+        .filter(|_| spawn.is_game_running())
+        .and_then(|(step, script)| script.steps.get(step))
+    {
+        Some(step) => step,
+        None => {
+            set_text(&mut commands, None);
+            return;
+        }
+    };
+
+    let actions: Vec<_> = input.iter().copied().collect();
+    let mut wave_ended = false;
+    for ev in wave.iter() {
+        match ev {
+            WaveEvent::Started => {
+                state.progress = 0;
+                state.completed = false;
+                set_text(&mut commands, step.hint.clone());
+            }
+            WaveEvent::Ended => wave_ended = true,
+            WaveEvent::Restart => (),
+        }
+    }
+    let dashes = kinematic
+        .iter()
+        .filter(|(entity, cmd)| {
+            matches!(cmd, KinematicCommand::Dash { .. }) && player.contains(*entity)
+        })
+        .count();
+
+    let mut next_step = false;
+    if state.completed {
+        next_step = actions.contains(&InputAction::Respawn);
+    } else {
+        let done = match step.until {
+            TutorialCondition::PressKey(action) => actions.contains(&action),
+            TutorialCondition::Dash(count) => {
+                state.progress += dashes;
+                state.progress >= count
+            }
+            TutorialCondition::KillAll => wave_ended,
+        };
+        if done {
+            if step.confirm {
+                state.completed = true;
+                set_text(
+                    &mut commands,
+                    Some(format!(
+                        "Press [{}] to continue",
+                        input_map.map[InputAction::Respawn].0.to_string()
+                    )),
+                );
+            } else {
+                next_step = true;
+            }
+        }
+    }
+
+    if next_step {
+        let count = scripts
+            .get(&assets.tutorial)
+            .map(|script| script.steps.len())
+            .unwrap_or_default();
+        state.step = state.step.map(|step| step + 1).filter(|step| *step < count);
+        state.progress = 0;
+        state.completed = false;
+        spawn.despawn = Some(true);
+        set_text(&mut commands, None);
+    }
+}
+
+fn draw_tutorial_text(
+    mut ctx: ResMut<EguiContext>, state: Res<TutorialState>, spawn: Res<SpawnControl>,
+    scripts: Res<Assets<TutorialScript>>, assets: Res<MyAssets>,
+) {
+    let step = match state
+        .step
+        .zip(scripts.get(&assets.tutorial))
+        .filter(|_| spawn.is_game_running())
+        .and_then(|(step, script)| script.steps.get(step))
+    {
+        Some(step) => step,
+        None => return,
+    };
+    ctx.popup(
+        "draw_tutorial_text",
+        vec2(0., 1.),
+        false,
+        egui::Order::Background,
+        |ui| {
+            ui.visuals_mut().override_text_color = Some(egui::Color32::WHITE);
+            ui.heading(&step.text);
+        },
+    );
+}
@@ -0,0 +1,227 @@
+use super::{
+    loot::{CraftPart, Loot},
+    spawn::TurretType,
+    weapon::WeaponModifier,
+};
+use crate::{assets::RonAsset, common::*, mechanics::ai::MoveBehaviour, settings::Difficulty};
+use bevy::{
+    asset::{AssetPath, LoadContext},
+    reflect::TypeUuid,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Asset - all waves of the campaign, in order.
+/// Last one is followed by the procedurally generated ones.
+#[derive(Clone, Default, Serialize, Deserialize, TypeUuid)]
+#[uuid = "3f8a119e-3070-4d9c-9c04-81014621e96e"]
+pub struct WaveList {
+    pub waves: Vec<WaveDefinition>,
+}
+
+impl RonAsset for WaveList {
+    const EXTENSIONS: &'static [&'static str] = &["waves.ron"];
+
+    fn resolve(&mut self, load_context: &mut LoadContext) -> Vec<AssetPath<'static>> {
+        for (index, wave) in self.waves.iter_mut().enumerate() {
+            wave.validate(&format!("{:?}, wave {}", load_context.path(), index + 1))
+        }
+        vec![]
+    }
+}
+
+/// Positions are normalized: (-1, -1) is bottom-left corner of the arena, (1, 1) is top-right.
+/// Sizes are in world units.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WaveDefinition {
+    pub walls: Vec<WallDefinition>,
+    pub turrets: Vec<TurretDefinition>,
+    pub boss: Option<BossPlacement>,
+    pub asteroids: Vec<AsteroidDefinition>,
+    /// Used for turrets which don't have their own
+    pub loot: LootTable,
+}
+
+impl WaveDefinition {
+    /// Fixes invalid values, logging them as errors
+    pub fn validate(&mut self, name: &str) {
+        let tables = std::iter::once(&mut self.loot)
+            .chain(self.turrets.iter_mut().filter_map(|v| v.loot.as_mut()))
+            .chain(self.asteroids.iter_mut().filter_map(|v| v.loot.as_mut()));
+        for table in tables {
+            table.validate(name)
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WallDefinition {
+    pub pos: Vec2,
+    pub size: Vec2,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TurretDefinition {
+    pub pos: Vec2,
+    pub ty: TurretType,
+    #[serde(default)]
+    pub loot: Option<LootTable>,
+    /// Only for mobile enemies. Patrol points are normalized.
+    #[serde(default)]
+    pub movement: Option<MoveBehaviour>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BossPlacement {
+    pub pos: Vec2,
+    /// See `BossList`
+    #[serde(default = "BossPlacement::default_id")]
+    pub id: String,
+}
+
+impl BossPlacement {
+    pub fn new(pos: Vec2) -> Self {
+        Self {
+            pos,
+            id: Self::default_id(),
+        }
+    }
+
+    fn default_id() -> String {
+        "the_boss".to_string()
+    }
+}
+
+/// See `Asteroid`. They don't need to be destroyed to end the wave.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AsteroidDefinition {
+    pub pos: Vec2,
+    /// In world units per second
+    #[serde(default)]
+    pub velocity: Vec2,
+    /// How many times it splits
+    #[serde(default = "AsteroidDefinition::default_size")]
+    pub size: u32,
+    /// Drops nothing if not set
+    #[serde(default)]
+    pub loot: Option<LootTable>,
+}
+
+impl AsteroidDefinition {
+    fn default_size() -> u32 {
+        2
+    }
+}
+
+/// What enemy drops on death
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LootTable {
+    /// Chance to drop anything at all
+    pub chance_easy: f64,
+    pub chance_hard: f64,
+
+    pub health_chance: f64,
+    pub health_easy: f32,
+    pub health_hard: f32,
+
+    pub craft_part_chance: f64,
+    pub modifier_chance: f64,
+}
+
+impl Default for LootTable {
+    fn default() -> Self {
+        Self {
+            chance_easy: 1.,
+            chance_hard: 0.8,
+            health_chance: 0.66,
+            health_easy: 4.,
+            health_hard: 1.5,
+            craft_part_chance: 0.33,
+            modifier_chance: 0.05,
+        }
+    }
+}
+
+impl LootTable {
+    /// Chances must be in [0; 1] range
+    fn validate(&mut self, name: &str) {
+        for (field, chance) in [
+            ("chance_easy", &mut self.chance_easy),
+            ("chance_hard", &mut self.chance_hard),
+            ("health_chance", &mut self.health_chance),
+            ("craft_part_chance", &mut self.craft_part_chance),
+            ("modifier_chance", &mut self.modifier_chance),
+        ] {
+            if !(0. ..=1.).contains(chance) {
+                log::error!(
+                    "{}: loot {} is {}, must be in [0; 1] range",
+                    name,
+                    field,
+                    chance
+                );
+                *chance = if chance.is_nan() { 0. } else { chance.clamp(0., 1.) };
+            }
+        }
+    }
+
+    pub fn roll(&self, difficulty: Difficulty, rng: &mut impl Rng) -> Vec<Loot> {
+        let mut loot = vec![];
+        if rng.gen_bool(match difficulty {
+            Difficulty::Easy => self.chance_easy,
+            Difficulty::Hard => self.chance_hard,
+        }) {
+            if rng.gen_bool(self.health_chance) {
+                loot.push(Loot::Health {
+                    value: match difficulty {
+                        Difficulty::Easy => self.health_easy,
+                        Difficulty::Hard => self.health_hard,
+                    },
+                });
+            }
+            if rng.gen_bool(self.craft_part_chance) {
+                loot.push(Loot::CraftPart(CraftPart::random(rng)));
+            }
+            if rng.gen_bool(self.modifier_chance) {
+                loot.push(Loot::Modifier(WeaponModifier::random(rng)));
+            }
+        }
+        loot
+    }
+}
+
+/// Where gameplay happens
+#[derive(Clone, Copy)]
+pub struct Arena {
+    /// Center of the arena
+    pub offset: Vec2,
+    /// Full size
+    pub size: Vec2,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        let offset = vec2(1.8, 0.);
+        Self {
+            offset,
+            size: Self::camera_size() - offset.abs() * 2.,
+        }
+    }
+}
+
+impl Arena {
+    /// Size of the world visible by camera
+    pub fn camera_size() -> Vec2 {
+        let world_ratio = 16. / 9.;
+        vec2(40., 40. / world_ratio)
+    }
+
+    pub fn to_world(&self, normalized: Vec2) -> Vec2 {
+        self.offset + normalized * self.size / 2.
+    }
+
+    pub fn to_normalized(&self, world: Vec2) -> Vec2 {
+        (world - self.offset) / (self.size / 2.)
+    }
+}
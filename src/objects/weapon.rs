@@ -1,11 +1,10 @@
-use super::stats::Stats;
+use super::{stats::Stats, weapon_def::WeaponList};
 use crate::{
     common::*,
     mechanics::{
         damage::*,
-        health::{Damage, DieAfter, Health},
+        health::{Damage, DieAfter, Health, ReceivedDamage},
         movement::KinematicController,
-        physics::CollectContacts,
     },
     objects::player::Player,
     present::{
@@ -14,18 +13,18 @@ use crate::{
         sound::{Beats, Sound},
     },
 };
+use serde::{Deserialize, Serialize};
 use std::f32::consts::PI;
 
 /// Command event
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Default)]
 pub enum Weapon {
     #[default]
     None,
-    Turret,
-    AdvancedTurret {
-        offset: f32,
+    /// Defined in `WeaponList`
+    Projectile {
+        id: String,
     },
-    RotatingTurret,
 
     PlayerGun {
         dir: Vec2,
@@ -33,9 +32,14 @@ pub enum Weapon {
     PlayerCrafted {
         dir: Vec2,
     },
+    PlayerCharged {
+        dir: Vec2,
+        /// In [0; 1] range
+        charge: f32,
+    },
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub enum CraftedWeapon {
     Plasma,
     Shield,
@@ -59,14 +63,134 @@ impl CraftedWeapon {
     }
 }
 
+/// Crafted weapon owned by the player
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WeaponInstance {
+    pub weapon: CraftedWeapon,
+    pub uses: f32,
+    #[serde(default)]
+    pub modifiers: Vec<WeaponModifier>,
+    /// Max uses without modifiers, if they differ from the default for that weapon
+    #[serde(default)]
+    pub base_uses: Option<f32>,
+}
+
+impl WeaponInstance {
+    pub fn new(weapon: CraftedWeapon, uses: f32) -> Self {
+        Self {
+            weapon,
+            uses,
+            modifiers: vec![],
+            base_uses: None,
+        }
+    }
+
+    /// Crafted from a recipe which sets its own number of uses
+    pub fn with_max_uses(mut self, uses: f32) -> Self {
+        self.base_uses = Some(uses);
+        self
+    }
+
+    fn base_max_uses(&self) -> f32 {
+        self.base_uses.unwrap_or(self.weapon.description().2)
+    }
+
+    /// How many times that modifier was applied
+    pub fn count(&self, modifier: WeaponModifier) -> usize {
+        self.modifiers.iter().filter(|m| **m == modifier).count()
+    }
+
+    pub fn max_uses(&self) -> f32 {
+        self.base_max_uses() * (1. + 0.5 * self.count(WeaponModifier::Durability) as f32)
+    }
+
+    pub fn can_apply(&self, modifier: WeaponModifier) -> bool {
+        modifier.applies_to(self.weapon) && self.count(modifier) < WeaponModifier::MAX_STACK
+    }
+
+    /// Returns false if modifier can't be applied
+    pub fn apply(&mut self, modifier: WeaponModifier) -> bool {
+        if !self.can_apply(modifier) {
+            return false;
+        }
+        if modifier == WeaponModifier::Durability {
+            self.uses += self.base_max_uses() * 0.5;
+        }
+        self.modifiers.push(modifier);
+        true
+    }
+
+    /// Like "Railgun +Pierce x2 +Durability"
+    pub fn name(&self) -> String {
+        let mut name = self.weapon.description().0.to_string();
+        for modifier in WeaponModifier::ALL {
+            match self.count(modifier) {
+                0 => (),
+                1 => name += &format!(" +{}", modifier.description().0),
+                count => name += &format!(" +{} x{}", modifier.description().0, count),
+            }
+        }
+        name
+    }
+}
+
+/// Upgrade of a single crafted weapon
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum WeaponModifier {
+    /// Railgun ray passes through more targets without losing damage
+    Pierce,
+    /// Bigger explosion or repel radius
+    BlastRadius,
+    /// More uses
+    Durability,
+    /// Faster projectiles
+    Velocity,
+}
+
+impl WeaponModifier {
+    pub const ALL: [Self; 4] = [
+        Self::Pierce,
+        Self::BlastRadius,
+        Self::Durability,
+        Self::Velocity,
+    ];
+    /// Max count of the same modifier on a weapon
+    pub const MAX_STACK: usize = 3;
+
+    pub fn random(rng: &mut impl rand::Rng) -> Self {
+        Self::ALL.into_iter().random(rng)
+    }
+
+    // (name, description, price in points)
+    pub fn description(&self) -> (&'static str, &'static str, usize) {
+        match self {
+            WeaponModifier::Pierce => ("Pierce", "Ray pierces one more target at full damage", 300),
+            WeaponModifier::BlastRadius => ("Blast", "Increases explosion radius", 250),
+            WeaponModifier::Durability => ("Durability", "Increases number of uses", 150),
+            WeaponModifier::Velocity => ("Velocity", "Increases projectile speed", 200),
+        }
+    }
+
+    pub fn applies_to(&self, weapon: CraftedWeapon) -> bool {
+        match self {
+            WeaponModifier::Pierce => matches!(weapon, CraftedWeapon::Railgun),
+            WeaponModifier::BlastRadius | WeaponModifier::Velocity => {
+                matches!(weapon, CraftedWeapon::Plasma | CraftedWeapon::Repeller)
+            }
+            WeaponModifier::Durability => true,
+        }
+    }
+}
+
 //
 
 pub struct WeaponPlugin;
 
 impl Plugin for WeaponPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<(Entity, Weapon)>()
-            .add_system_to_stage(CoreStage::PostUpdate, weapon);
+        app.add_game_event::<(Entity, Weapon)>()
+            .add_game_system(GameStage::PostUpdate, weapon)
+            .add_game_system(GameStage::PostUpdate, shield_hit);
     }
 }
 
@@ -79,182 +203,42 @@ fn weapon(
         Option<&KinematicController>,
     )>,
     mut sound_cmd: EventWriter<Sound>, assets: Res<MyAssets>, beats: Res<Beats>,
-    real_time: Res<Time>, mut stats: ResMut<Stats>,
+    time: Res<GameTime>, mut stats: ResMut<Stats>, weapons: Res<Assets<WeaponList>>,
 ) {
     use bevy_lyon::*;
     weapon.iter_cmd_mut(
         &mut source,
-        |weapon, (_entity, transform, team, kinematic)| match *weapon {
+        |weapon, (entity, transform, team, kinematic)| match weapon {
             Weapon::None => log::warn!("Shooting Weapon::None"),
 
-            Weapon::Turret => {
-                let radius = 0.25;
-                let (transform, velocity) = make_origin(transform, 1.5, 10., 0.);
-                commands
-                    .spawn_bundle(GeometryBuilder::build_as(
-                        &shapes::Polygon {
-                            points: vec![
-                                vec2(0., radius * 2.),
-                                vec2(0., radius).rotated(160f32.to_radians()),
-                                vec2(0., radius).rotated(-160f32.to_radians()),
-                            ],
-                            closed: true,
-                        },
-                        DrawMode::Fill(FillMode::color(Color::rgb(1., 1., 0.6))),
-                        transform,
-                    ))
-                    .insert(Depth::Projectile)
-                    .insert(Light {
-                        radius: 2.,
-                        color: Color::rgb(1., 1., 0.8).with_a(0.07),
-                    })
-                    .insert(
-                        Explosion {
-                            origin: Vec2::ZERO,
-                            color0: Color::YELLOW,
-                            color1: Color::RED,
-                            time: Duration::from_millis(400),
-                            radius: 0.5,
-                            power: ExplosionPower::Small,
-                        }
-                        .death(),
-                    )
-                    //
-                    .insert(GameplayObject)
-                    .insert(SmallProjectile)
-                    .insert(Damage::new(1.))
-                    .insert(*team)
-                    .insert(DamageOnContact)
-                    .insert(DieOnContact)
-                    .insert(CollectContacts::default())
-                    .insert(Health::new(0.1))
-                    //
-                    .insert(RigidBody::Dynamic)
-                    .insert(Collider::ball(radius))
-                    .insert(ColliderMassProperties::Mass(1.))
-                    .insert(PhysicsType::Projectile.rapier())
-                    .insert(Velocity::linear(velocity));
-
-                sound_cmd.send(Sound {
-                    sound: assets.wpn_smg.clone(),
-                    position: Some(transform.pos_2d()),
-                    ..default()
-                });
-            }
-
-            Weapon::AdvancedTurret { offset } => {
-                let radius = 0.3;
-                let (transform, velocity) = make_origin(transform, 1.5, 7.5, offset);
-                commands
-                    .spawn_bundle(GeometryBuilder::build_as(
-                        &shapes::Polygon {
-                            points: vec![
-                                vec2(0., radius * 2.),
-                                vec2(0., radius).rotated(160f32.to_radians()),
-                                vec2(0., radius).rotated(-160f32.to_radians()),
-                            ],
-                            closed: true,
-                        },
-                        DrawMode::Fill(FillMode::color(Color::rgb(1., 0.3, 0.3))),
-                        transform,
-                    ))
-                    .insert(Depth::Projectile)
-                    .insert(Light {
-                        radius: 2.,
-                        color: Color::RED.with_a(0.07),
-                    })
-                    .insert(
-                        Explosion {
-                            origin: Vec2::ZERO,
-                            color0: Color::YELLOW,
-                            color1: Color::RED,
-                            time: Duration::from_millis(400),
-                            radius: 0.5,
-                            power: ExplosionPower::Small,
-                        }
-                        .death(),
-                    )
-                    //
-                    .insert(GameplayObject)
-                    .insert(SmallProjectile)
-                    .insert(Damage::new(1.))
-                    .insert(*team)
-                    .insert(DamageOnContact)
-                    .insert(DieOnContact)
-                    .insert(CollectContacts::default())
-                    .insert(Health::new(0.1))
-                    //
-                    .insert(RigidBody::Dynamic)
-                    .insert(Collider::ball(radius))
-                    .insert(ColliderMassProperties::Mass(1.))
-                    .insert(PhysicsType::Projectile.rapier())
-                    .insert(Velocity::linear(velocity));
-
-                sound_cmd.send(Sound {
-                    sound: assets.wpn_smg.clone(),
-                    position: Some(transform.pos_2d()),
-                    ..default()
-                });
-            }
-
-            Weapon::RotatingTurret => {
-                let radius = 0.4;
-                let (transform, velocity) = make_origin(transform, 1.5, 6., 0.);
-                commands
-                    .spawn_bundle(GeometryBuilder::build_as(
-                        &shapes::Circle {
-                            radius,
-                            center: Vec2::ZERO,
-                        },
-                        DrawMode::Fill(FillMode::color(Color::rgb(0.3, 1., 0.6))),
-                        transform,
-                    ))
-                    .insert(Depth::Projectile)
-                    .insert(Light {
-                        radius: 2.,
-                        color: Color::LIME_GREEN.with_a(0.07),
-                    })
-                    .insert(
-                        Explosion {
-                            origin: Vec2::ZERO,
-                            color0: Color::GREEN,
-                            color1: Color::YELLOW,
-                            time: Duration::from_millis(400),
-                            radius: 0.5,
-                            power: ExplosionPower::None,
-                        }
-                        .death(),
-                    )
-                    //
-                    .insert(GameplayObject)
-                    .insert(Damage::new(1.))
-                    .insert(*team)
-                    .insert(DamageOnContact)
-                    .insert(DieOnContact)
-                    .insert(BigProjectile)
-                    .insert(CollectContacts::default())
-                    .insert(Health::new(2.))
-                    //
-                    .insert(RigidBody::Dynamic)
-                    .insert(Collider::ball(radius))
-                    .insert(ColliderMassProperties::Mass(3.))
-                    .insert(PhysicsType::Projectile.rapier())
-                    .insert(Velocity::linear(velocity));
+            Weapon::Projectile { id } => {
+                let def = match weapons.get(&assets.weapons).and_then(|list| list.get(id)) {
+                    Some(def) => def,
+                    None => return,
+                };
+                let forward = Vec2::Y.rotated(transform.angle_2d());
+                for (projectile, velocity) in def.origins(transform.pos_2d(), forward) {
+                    let mut commands = commands.spawn();
+                    commands.insert(GameplayObject).insert(Owner(entity));
+                    def.insert_projectile(&mut commands, projectile, velocity, *team);
+                }
 
                 sound_cmd.send(Sound {
-                    sound: assets.wpn_plasma.clone(),
+                    sound: def.sound_handle.clone(),
                     position: Some(transform.pos_2d()),
                     ..default()
                 });
             }
 
-            Weapon::PlayerGun { dir } | Weapon::PlayerCrafted { dir } => {
+            Weapon::PlayerGun { dir }
+            | Weapon::PlayerCrafted { dir }
+            | Weapon::PlayerCharged { dir, .. } => {
                 let dir = dir.try_normalize().unwrap_or(Vec2::Y);
                 let mut transform = Transform::new_2d(transform.pos_2d() + dir * Player::RADIUS);
                 let angle = dir.angle();
                 transform.set_angle_2d(angle);
 
-                let ultra_powered = beats.in_beat(&real_time);
+                let ultra_powered = beats.in_beat(&time);
                 let powered = ultra_powered
                     || angle_delta(
                         angle,
@@ -266,7 +250,10 @@ fn weapon(
                         < 60f32.to_radians();
 
                 let mut commands = commands.spawn_bundle(SpatialBundle::from_transform(transform));
-                commands.insert(GameplayObject).insert(*team);
+                commands
+                    .insert(GameplayObject)
+                    .insert(*team)
+                    .insert(Owner(entity));
 
                 let mut explodes_projectiles = powered || ultra_powered;
 
@@ -300,6 +287,32 @@ fn weapon(
                             assets.player_gun.clone()
                         },
                     ),
+                    Weapon::PlayerCharged { charge, .. } => {
+                        let charge = *charge;
+                        explodes_projectiles |= charge >= 1.;
+                        (
+                            [2., 4., 8.].map(|damage| damage * (1. + charge * 2.)),
+                            Some(DamageRay {
+                                spawn_effect: Some(RayEffect {
+                                    color: Color::rgb(1., 0.9, 0.5).with_a(0.5),
+                                    width: lerp(0.4, 1., charge),
+                                    fade_time: Duration::from_millis(500),
+                                    ..default()
+                                }),
+                                explosion_effect: Some(Explosion {
+                                    color0: Color::WHITE,
+                                    color1: Color::YELLOW,
+                                    time: Duration::from_millis(400),
+                                    radius: lerp(1., 2., charge),
+                                    ..default()
+                                }),
+                                // only when fully charged
+                                pierce: (charge >= 1.) as usize,
+                                ..default()
+                            }),
+                            assets.player_gun_powered.clone(),
+                        )
+                    }
                     _ => {
                         let mega_weapon = match stats.player.weapon0.as_mut() {
                             Some(w) => w,
@@ -308,18 +321,23 @@ fn weapon(
                                 return;
                             }
                         };
-                        match mega_weapon {
-                            (CraftedWeapon::Railgun, uses) => {
-                                *uses -= 1.;
-                                if *uses <= 0. {
-                                    stats.player.weapon0 = None;
-                                    sound_cmd.send(Sound {
-                                        sound: assets.ui_weapon_broken.clone(),
-                                        non_randomized: true,
-                                        ..default()
-                                    });
-                                }
+                        mega_weapon.uses -= 1.;
+                        let mega_weapon = mega_weapon.clone();
+                        if mega_weapon.uses <= 0. {
+                            stats.player.weapon0 = None;
+                            sound_cmd.send(Sound {
+                                sound: assets.ui_weapon_broken.clone(),
+                                non_randomized: true,
+                                ..default()
+                            });
+                        }
 
+                        let blast =
+                            1. + 0.3 * mega_weapon.count(WeaponModifier::BlastRadius) as f32;
+                        let speed_factor =
+                            1. + 0.3 * mega_weapon.count(WeaponModifier::Velocity) as f32;
+                        match mega_weapon.weapon {
+                            CraftedWeapon::Railgun => {
                                 explodes_projectiles = true;
                                 (
                                     [4., 6., 12.],
@@ -345,23 +363,33 @@ fn weapon(
                                             radius: 1.5,
                                             ..default()
                                         }),
+                                        pierce: mega_weapon.count(WeaponModifier::Pierce),
                                         ..default()
                                     }),
                                     assets.player_railgun.clone(),
                                 )
                             }
-                            (CraftedWeapon::Plasma, uses) => {
-                                *uses -= 1.;
-                                if *uses <= 0. {
-                                    stats.player.weapon0 = None;
-                                    sound_cmd.send(Sound {
-                                        sound: assets.ui_weapon_broken.clone(),
-                                        non_randomized: true,
-                                        ..default()
-                                    });
+                            CraftedWeapon::Plasma => {
+                                let def = match weapons
+                                    .get(&assets.weapons)
+                                    .and_then(|list| list.get("plasma"))
+                                {
+                                    Some(def) => def,
+                                    None => {
+                                        commands.insert(DieAfter::one_frame());
+                                        return;
+                                    }
+                                };
+
+                                let mut def = def.clone();
+                                if let Some(effect) = def.death_effect.as_mut() {
+                                    effect.radius *= blast
+                                }
+                                if let Some(explode) = def.explode.as_mut() {
+                                    explode.radius *= blast
                                 }
 
-                                let mut speed = 8.;
+                                let mut speed = speed_factor;
                                 if powered {
                                     speed *= 2.
                                 }
@@ -369,54 +397,93 @@ fn weapon(
                                     speed *= 1.5
                                 }
 
-                                let radius = 0.7;
-                                transform.add_2d(dir * (radius + 0.1));
+                                // damage is set below; only the first projectile is used
+                                if let Some((projectile, velocity)) =
+                                    def.origins(transform.pos_2d(), dir).into_iter().next()
+                                {
+                                    transform = projectile;
+                                    def.insert_projectile(
+                                        &mut commands,
+                                        projectile,
+                                        velocity * speed,
+                                        *team,
+                                    );
+                                }
+
+                                ([2., 5., 10.], None, assets.player_plasma.clone())
+                            }
+                            CraftedWeapon::Shield => {
+                                let mut lifetime = Duration::from_secs(4);
+                                if powered {
+                                    lifetime *= 2
+                                }
+                                let health = if ultra_powered { 8. } else { 5. };
+
+                                let size = vec2(3.5, 0.3);
+                                transform.add_2d(dir * 1.5);
 
                                 commands
                                     .insert_bundle(GeometryBuilder::build_as(
-                                        &shapes::Circle {
-                                            radius,
-                                            center: Vec2::ZERO,
+                                        &shapes::Rectangle {
+                                            extents: size,
+                                            origin: RectangleOrigin::Center,
+                                        },
+                                        DrawMode::Outlined {
+                                            fill_mode: FillMode::color(
+                                                Color::rgb(0.3, 0.7, 1.).with_a(0.3),
+                                            ),
+                                            outline_mode: StrokeMode::new(
+                                                Color::rgb(0.6, 0.9, 1.),
+                                                0.05,
+                                            ),
                                         },
-                                        DrawMode::Fill(FillMode::color(Color::rgb(0.4, 1., 0.3))),
                                         transform,
                                     ))
                                     .insert(Depth::Projectile)
                                     .insert(Light {
-                                        radius: 2.,
-                                        color: Color::LIME_GREEN.with_a(0.07),
+                                        radius: 3.,
+                                        color: Color::CYAN.with_a(0.05),
                                     })
                                     //
-                                    .insert(GameplayObject)
-                                    .insert(BigProjectile)
-                                    .insert(Team::YEEEEEEE)
-                                    .insert(DamageOnContact)
-                                    .insert(DieOnContact)
-                                    .insert(CollectContacts::default())
-                                    .insert(Health::new(3.))
-                                    .insert(ExplodeOnDeath {
-                                        damage: 1.,
-                                        radius: 3.,
-                                        effect: Explosion {
-                                            origin: Vec2::ZERO,
-                                            color0: Color::GREEN,
-                                            color1: Color::RED,
-                                            time: Duration::from_millis(400),
-                                            radius: 3.,
-                                            power: ExplosionPower::Small,
-                                        },
-                                        activated: false,
+                                    .insert(ForceField {
+                                        ray_absorb: 0.6,
+                                        recharge: 1.,
                                     })
+                                    .insert(Health::new(health))
+                                    .insert(DieAfter::new(lifetime))
                                     //
-                                    .insert(RigidBody::Dynamic)
-                                    .insert(Collider::ball(radius))
-                                    .insert(ColliderMassProperties::Mass(2.))
-                                    .insert(PhysicsType::Solid.rapier())
-                                    .insert(Velocity::linear(dir * speed));
+                                    .insert(RigidBody::Fixed)
+                                    .insert(Collider::cuboid(size.x / 2., size.y / 2.))
+                                    .insert(Sensor)
+                                    .insert(PhysicsType::ForceField.rapier());
 
-                                ([2., 5., 10.], None, assets.player_plasma.clone())
+                                ([0., 0., 0.], None, assets.player_shield.clone())
+                            }
+                            CraftedWeapon::Repeller => {
+                                let radius = if powered { 6. } else { 4.5 } * blast;
+                                let speed = if ultra_powered { 15. } else { 10. } * speed_factor;
+
+                                commands
+                                    .insert(Repel {
+                                        radius,
+                                        speed,
+                                        activate: powered || ultra_powered,
+                                    })
+                                    .insert(
+                                        Explosion {
+                                            origin: Vec2::ZERO,
+                                            color0: Color::CYAN,
+                                            color1: Color::WHITE,
+                                            time: Duration::from_millis(300),
+                                            radius,
+                                            power: ExplosionPower::None,
+                                        }
+                                        .death(),
+                                    )
+                                    .insert(DieAfter::one_frame());
+
+                                ([0., 0., 0.], None, assets.player_shield.clone())
                             }
-                            _ => todo!(),
                         }
                     }
                 };
@@ -451,9 +518,15 @@ fn weapon(
     );
 }
 
-fn make_origin(pos: &GlobalTransform, distance: f32, speed: f32, offset: f32) -> (Transform, Vec2) {
-    let mut pos: Transform = (*pos).into();
-    let forward = pos.local_y().truncate();
-    pos.add_2d(forward * distance + forward.clockwise90() * offset);
-    (pos, forward * speed)
+fn shield_hit(
+    mut received: CmdReader<ReceivedDamage>, mut fields: Query<&GlobalTransform, With<ForceField>>,
+    mut sound_cmd: EventWriter<Sound>, assets: Res<MyAssets>,
+) {
+    received.iter_cmd_mut(&mut fields, |_, pos| {
+        sound_cmd.send(Sound {
+            sound: assets.player_shield.clone(),
+            position: Some(pos.pos_2d()),
+            ..default()
+        })
+    });
 }
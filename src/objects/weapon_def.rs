@@ -0,0 +1,260 @@
+use crate::{
+    assets::{AppRonAsset, RonAsset},
+    common::*,
+    mechanics::{
+        damage::{
+            BigProjectile, DamageOnContact, DieOnContact, ExplodeOnDeath, SmallProjectile, Team,
+        },
+        health::{Damage, Health},
+        physics::CollectContacts,
+    },
+    present::{
+        effect::{Explosion, ExplosionPower},
+        light::Light,
+    },
+};
+use bevy::{
+    asset::{AssetPath, LoadContext},
+    ecs::system::EntityCommands,
+    reflect::TypeUuid,
+};
+use bevy_kira_audio::AudioSource;
+use serde::Deserialize;
+
+/// Asset - projectile weapons, referred by `Weapon::Projectile`
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "b0a3e6f2-4c1d-4d7e-8f5a-6e2d9c1b7a30"]
+pub struct WeaponList {
+    pub weapons: HashMap<String, WeaponDef>,
+}
+
+impl RonAsset for WeaponList {
+    const EXTENSIONS: &'static [&'static str] = &["weapons.ron"];
+
+    fn resolve(&mut self, load_context: &mut LoadContext) -> Vec<AssetPath<'static>> {
+        self.weapons
+            .values_mut()
+            .filter_map(|def| {
+                let path = def.sound.as_ref()?;
+                def.sound_handle = load_context.get_handle(path.as_str());
+                Some(AssetPath::from(path.as_str()).to_owned())
+            })
+            .collect()
+    }
+}
+
+impl WeaponList {
+    /// Unknown IDs in behaviours are reported once, when they're loaded
+    pub fn get(&self, id: &str) -> Option<&WeaponDef> {
+        self.weapons.get(id)
+    }
+}
+
+/// Single shot spawns a projectile for each offset, multiplied by count
+#[derive(Clone, Deserialize)]
+pub struct WeaponDef {
+    pub shape: ProjectileShape,
+    pub color: Color,
+    /// Distance from the shooter
+    #[serde(default = "WeaponDef::default_distance")]
+    pub distance: f32,
+    pub speed: f32,
+    pub damage: f32,
+    pub health: f32,
+    pub mass: f32,
+    /// Big projectiles survive contacts with small ones
+    #[serde(default)]
+    pub big: bool,
+    /// Overrides team of the shooter
+    #[serde(default)]
+    pub team: Option<Team>,
+    #[serde(default = "WeaponDef::default_physics")]
+    pub physics: PhysicsType,
+
+    #[serde(default)]
+    pub light: Option<Light>,
+    #[serde(default)]
+    pub death_effect: Option<ExplosionDef>,
+    #[serde(default)]
+    pub explode: Option<ExplodeDef>,
+
+    /// Path to the sound asset
+    #[serde(default)]
+    pub sound: Option<String>,
+    #[serde(skip)]
+    pub sound_handle: Handle<AudioSource>,
+
+    /// Sideways offsets of parallel projectiles
+    #[serde(default = "WeaponDef::default_offsets")]
+    pub offsets: Vec<f32>,
+    /// Projectiles for each offset, spread evenly
+    #[serde(default = "WeaponDef::default_count")]
+    pub count: usize,
+    /// Angle between first and last projectile, in degrees
+    #[serde(default)]
+    pub spread: f32,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+pub enum ProjectileShape {
+    Triangle { radius: f32 },
+    Circle { radius: f32 },
+}
+
+impl ProjectileShape {
+    pub fn radius(self) -> f32 {
+        match self {
+            ProjectileShape::Triangle { radius } | ProjectileShape::Circle { radius } => radius,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Deserialize)]
+pub struct ExplosionDef {
+    pub color0: Color,
+    pub color1: Color,
+    /// Seconds
+    pub time: f32,
+    pub radius: f32,
+    #[serde(default)]
+    pub power: ExplosionPower,
+}
+
+impl ExplosionDef {
+    pub fn explosion(self) -> Explosion {
+        Explosion {
+            origin: Vec2::ZERO,
+            color0: self.color0,
+            color1: self.color1,
+            time: Duration::from_secs_f32(self.time),
+            radius: self.radius,
+            power: self.power,
+        }
+    }
+}
+
+/// Uses death effect
+#[derive(Clone, Copy, Deserialize)]
+pub struct ExplodeDef {
+    pub damage: f32,
+    pub radius: f32,
+}
+
+impl WeaponDef {
+    fn default_distance() -> f32 {
+        1.5
+    }
+    fn default_physics() -> PhysicsType {
+        PhysicsType::Projectile
+    }
+    fn default_offsets() -> Vec<f32> {
+        vec![0.]
+    }
+    fn default_count() -> usize {
+        1
+    }
+
+    /// Transform and velocity of each projectile, relative to the shooter's forward direction
+    pub fn origins(&self, pos: Vec2, forward: Vec2) -> Vec<(Transform, Vec2)> {
+        let forward = forward.try_normalize().unwrap_or(Vec2::Y);
+        let spread = self.spread.to_radians();
+        let mut origins = vec![];
+        for offset in &self.offsets {
+            for i in 0..self.count {
+                let angle = if self.count > 1 {
+                    lerp(
+                        -spread / 2.,
+                        spread / 2.,
+                        i as f32 / (self.count - 1) as f32,
+                    )
+                } else {
+                    0.
+                };
+                let dir = forward.rotated(angle);
+                let mut transform = Transform::new_2d(
+                    pos + forward * self.distance + forward.clockwise90() * *offset,
+                );
+                transform.set_angle_2d(dir.angle());
+                origins.push((transform, dir * self.speed));
+            }
+        }
+        origins
+    }
+
+    /// Adds everything except `GameplayObject`
+    pub fn insert_projectile(
+        &self, commands: &mut EntityCommands, transform: Transform, velocity: Vec2, team: Team,
+    ) {
+        use bevy_lyon::*;
+
+        let radius = self.shape.radius();
+        let draw_mode = DrawMode::Fill(FillMode::color(self.color));
+        match self.shape {
+            ProjectileShape::Triangle { .. } => commands.insert_bundle(GeometryBuilder::build_as(
+                &shapes::Polygon {
+                    points: vec![
+                        vec2(0., radius * 2.),
+                        vec2(0., radius).rotated(160f32.to_radians()),
+                        vec2(0., radius).rotated(-160f32.to_radians()),
+                    ],
+                    closed: true,
+                },
+                draw_mode,
+                transform,
+            )),
+            ProjectileShape::Circle { .. } => commands.insert_bundle(GeometryBuilder::build_as(
+                &shapes::Circle {
+                    radius,
+                    center: Vec2::ZERO,
+                },
+                draw_mode,
+                transform,
+            )),
+        };
+
+        commands.insert(Depth::Projectile);
+        if let Some(light) = self.light {
+            commands.insert(light);
+        }
+        if let Some(effect) = self.death_effect {
+            match self.explode {
+                Some(explode) => commands.insert(ExplodeOnDeath {
+                    damage: explode.damage,
+                    radius: explode.radius,
+                    effect: effect.explosion(),
+                    activated: false,
+                }),
+                None => commands.insert(effect.explosion().death()),
+            };
+        }
+
+        if self.big {
+            commands.insert(BigProjectile);
+        } else {
+            commands.insert(SmallProjectile);
+        }
+        commands
+            .insert(Damage::new(self.damage))
+            .insert(self.team.unwrap_or(team))
+            .insert(DamageOnContact)
+            .insert(DieOnContact)
+            .insert(CollectContacts::default())
+            .insert(Health::new(self.health))
+            //
+            .insert(RigidBody::Dynamic)
+            .insert(Collider::ball(radius))
+            .insert(ColliderMassProperties::Mass(self.mass))
+            .insert(self.physics.rapier())
+            .insert(Velocity::linear(velocity));
+    }
+}
+
+//
+
+pub struct WeaponDefPlugin;
+
+impl Plugin for WeaponDefPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_ron_asset::<WeaponList>();
+    }
+}
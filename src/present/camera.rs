@@ -1,4 +1,4 @@
-use crate::common::*;
+use crate::{common::*, control::replay::Replay};
 
 /// Resource - info about main window
 #[derive(Default)]
@@ -42,8 +42,12 @@ pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<WindowInfo>()
-            .add_startup_system(spawn_camera)
+        app.init_resource::<WindowInfo>();
+        // cursor is set directly
+        if app.is_headless() {
+            return;
+        }
+        app.add_startup_system(spawn_camera)
             .add_system(update_camera_scale)
             .add_system_to_stage(CoreStage::PreUpdate, update_window_info)
             .add_system(follow_target);
@@ -69,7 +73,7 @@ fn update_camera_scale(
 
 fn update_window_info(
     windows: Res<Windows>, camera: Query<(&Camera, &GlobalTransform), With<WorldCamera>>,
-    mut info: ResMut<WindowInfo>,
+    mut info: ResMut<WindowInfo>, replay: Res<Replay>,
 ) {
     let (camera, camera_transform) = camera.single();
     let window = windows.primary();
@@ -89,6 +93,10 @@ fn update_window_info(
     info.world_max = pos + halfsize;
 
     info.size = window_size;
+
+    if let Some(frame) = replay.frame() {
+        info.cursor = frame.cursor;
+    }
 }
 
 fn follow_target(
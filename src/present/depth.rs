@@ -31,9 +31,8 @@ impl Depth {
         }
     }
 
-    fn z_fuzzy(self) -> f32 {
-        use rand::*;
-        self.z_exact() + thread_rng().gen_range(0. ..1.)
+    fn z_fuzzy(self, rng: &mut impl rand::Rng) -> f32 {
+        self.z_exact() + rng.gen_range(0. ..1.)
     }
 }
 
@@ -52,10 +51,10 @@ impl Plugin for DepthPlugin {
 
 fn set_depth(
     mut entities: Query<(&mut Transform, &Depth, Option<&Parent>), Added<Depth>>,
-    parents: Query<(&Depth, Option<&Parent>)>,
+    parents: Query<(&Depth, Option<&Parent>)>, mut rng: ResMut<GameRng>,
 ) {
     for (mut transform, depth, parent) in entities.iter_mut() {
-        let mut z = depth.z_fuzzy();
+        let mut z = depth.z_fuzzy(rng.cosmetic());
 
         parent.map(|p| p.get()).while_some(|parent| {
             parents.get(parent).ok().and_then(|(depth, next_parent)| {
@@ -5,6 +5,7 @@ use crate::{
     common::*,
     mechanics::health::{Damage, DeathEvent, Health, ReceivedDamage},
 };
+use serde::Deserialize;
 
 // Event
 #[derive(Clone, Copy, Default)]
@@ -17,7 +18,7 @@ pub struct Explosion {
     pub power: ExplosionPower,
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Deserialize)]
 pub enum ExplosionPower {
     #[default]
     None,
@@ -76,6 +77,7 @@ pub enum FlashOnDamage {
     Radius(f32),
 }
 
+/// Removed when the time runs out; removing it earlier cancels the effect
 #[derive(Component, Clone, Copy)]
 pub struct ChargingAttack {
     pub radius: f32,
@@ -286,17 +288,19 @@ struct Spark {
 fn hit_sparks(
     mut commands: Commands, mut events: EventReader<HitSparks>,
     mut sparks: Query<(Entity, &Spark, &mut Transform)>, time: Res<GameTime>,
+    mut rng: ResMut<GameRng>,
 ) {
     // new sparks
     for event in events.iter() {
         use bevy_lyon::*;
         use rand::*;
 
+        let rng = rng.cosmetic();
         let count = (event.damage as usize * 3).min(10);
         for _ in 0..count {
-            let radius = thread_rng().gen_range(0.02..0.15);
-            let offset = thread_rng().gen_range(0.5..0.7);
-            let dir = Vec2::X.rotated(thread_rng().gen_range(0. ..TAU));
+            let radius = rng.gen_range(0.02..0.15);
+            let offset = rng.gen_range(0.5..0.7);
+            let dir = Vec2::X.rotated(rng.gen_range(0. ..TAU));
 
             let k_star = 0.3;
             commands
@@ -320,10 +324,10 @@ fn hit_sparks(
                 ))
                 .insert(Depth::ImportantEffect)
                 .insert(Spark {
-                    velocity: dir * thread_rng().gen_range(1. ..3.),
-                    angular: thread_rng().gen_range(-1. ..1.) * 2. * TAU,
+                    velocity: dir * rng.gen_range(1. ..3.),
+                    angular: rng.gen_range(-1. ..1.) * 2. * TAU,
                     start: time.now(),
-                    duration: Duration::from_secs_f32(thread_rng().gen_range(0.6..1.2)),
+                    duration: Duration::from_secs_f32(rng.gen_range(0.6..1.2)),
                 })
                 .insert(GameplayObject);
         }
@@ -335,8 +339,8 @@ fn hit_sparks(
         if t >= 1. {
             commands.entity(entity).despawn_recursive();
         } else {
-            transform.add_2d(spark.velocity * time.delta_seconds());
-            let new_angle = transform.angle_2d() + spark.angular * time.delta_seconds();
+            transform.add_2d(spark.velocity * time.frame_delta_seconds());
+            let new_angle = transform.angle_2d() + spark.angular * time.frame_delta_seconds();
             transform.set_angle_2d(new_angle);
             transform.scale = Vec3::new(1. - t, 1. - t, 1.);
         }
@@ -457,11 +461,24 @@ fn charging_attack(
         &ChargingAttack,
     )>,
     mut hints: Query<(&mut Transform, &mut DrawMode), Without<ChargingSpark>>, time: Res<GameTime>,
-    mut explode: EventWriter<Explosion>,
+    mut explode: EventWriter<Explosion>, mut rng: ResMut<GameRng>,
+    cancelled: Query<(Entity, &ChargingState), Without<ChargingAttack>>,
 ) {
     use bevy_lyon::*;
 
+    for (entity, state) in cancelled.iter() {
+        commands.entity(entity).remove::<ChargingState>();
+        commands.entity(state.inner).despawn_recursive();
+        commands.entity(state.outer).despawn_recursive();
+    }
+
     for (entity, attack) in new.iter() {
+        // re-added before the previous one was finished or cancelled
+        if let Ok((.., state, _)) = stat.get(entity) {
+            commands.entity(state.inner).despawn_recursive();
+            commands.entity(state.outer).despawn_recursive();
+        }
+
         let mut inner = BadEntityHack::default();
         let mut outer = BadEntityHack::default();
         commands
@@ -503,6 +520,9 @@ fn charging_attack(
     }
 
     for (entity, pos, mut state, attack) in stat.iter_mut() {
+        if new.contains(entity) {
+            continue;
+        }
         let pos = pos.pos_2d();
         let t = time.t_passed(state.start, attack.duration);
         if t >= 1. {
@@ -535,13 +555,14 @@ fn charging_attack(
             let new_count =
                 time.t_passed(state.start, Duration::from_secs_f32(lerp(0.15, 0.033, t))) as usize;
             for _ in state.count..new_count {
-                let offset = (Vec2::Y * attack.radius * thread_rng().gen_range(0.5..2.))
-                    .rotated(thread_rng().gen_range(0. ..TAU));
                 use rand::*;
+                let rng = rng.cosmetic();
+                let offset = (Vec2::Y * attack.radius * rng.gen_range(0.5..2.))
+                    .rotated(rng.gen_range(0. ..TAU));
                 commands
                     .spawn_bundle(GeometryBuilder::build_as(
                         &shapes::Circle {
-                            radius: thread_rng().gen_range(0.05..0.15),
+                            radius: rng.gen_range(0.05..0.15),
                             center: Vec2::ZERO,
                         },
                         DrawMode::Fill(FillMode::color(Color::NONE)),
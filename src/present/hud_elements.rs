@@ -19,8 +19,11 @@ pub struct HudElementsPlugin;
 
 impl Plugin for HudElementsPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<TheFont>()
-            .add_startup_system(load_the_font)
+        app.init_resource::<TheFont>();
+        if app.is_headless() {
+            return;
+        }
+        app.add_startup_system(load_the_font)
             .add_system(spawn_world_text);
     }
 }
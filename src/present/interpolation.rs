@@ -0,0 +1,79 @@
+use crate::{common::*, control::time::TickPropagateSystemLabel};
+
+/// Position of moving body at the last two ticks
+#[derive(Component)]
+struct TickTransform {
+    previous: (Vec2, f32),
+    current: (Vec2, f32),
+}
+
+impl TickTransform {
+    fn get(transform: &Transform) -> (Vec2, f32) {
+        (transform.pos_2d(), transform.angle_2d())
+    }
+
+    fn set(transform: &mut Transform, (pos, angle): (Vec2, f32)) {
+        transform.set_2d(pos);
+        transform.set_angle_2d(angle);
+    }
+}
+
+//
+
+/// Smoothes movement of physical bodies when render framerate differs from the tick rate.
+/// Only XY position and rotation are interpolated.
+pub struct InterpolationPlugin;
+
+impl Plugin for InterpolationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_game_system(GameStage::First, restore.before(TickPropagateSystemLabel))
+            .add_game_system(GameStage::Last, save)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                interpolate.before(bevy::transform::TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// Gameplay and physics must see actual positions, not interpolated ones
+fn restore(mut entities: Query<(&mut Transform, &TickTransform)>) {
+    for (mut transform, tick) in entities.iter_mut() {
+        TickTransform::set(&mut transform, tick.current)
+    }
+}
+
+fn save(
+    mut commands: Commands,
+    mut entities: Query<
+        (Entity, &Transform, &RigidBody, Option<&mut TickTransform>),
+        Without<Parent>,
+    >,
+) {
+    for (entity, transform, body, tick) in entities.iter_mut() {
+        let value = TickTransform::get(transform);
+        match tick {
+            Some(mut tick) => {
+                tick.previous = tick.current;
+                tick.current = value;
+            }
+            None => {
+                if !matches!(body, RigidBody::Fixed) {
+                    commands.entity(entity).insert(TickTransform {
+                        previous: value,
+                        current: value,
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn interpolate(mut entities: Query<(&mut Transform, &TickTransform)>, time: Res<GameTime>) {
+    let t = time.tick_fraction();
+    for (mut transform, tick) in entities.iter_mut() {
+        let (pos0, angle0) = tick.previous;
+        let (pos1, angle1) = tick.current;
+        let angle = angle0 + angle_delta(angle1, angle0) * t;
+        TickTransform::set(&mut transform, (lerp(pos0, pos1, t), angle));
+    }
+}
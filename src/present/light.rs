@@ -1,6 +1,7 @@
 use crate::common::*;
+use serde::Deserialize;
 
-#[derive(Component, Default, Clone, Copy)]
+#[derive(Component, Default, Clone, Copy, Deserialize)]
 pub struct Light {
     pub radius: f32,
     pub color: Color,
@@ -72,7 +73,10 @@ fn update_light(
     }
 }
 
-fn light_pulse(mut entities: Query<(&mut Light, &mut LightPulse)>, time: Res<GameTime>) {
+fn light_pulse(
+    mut entities: Query<(&mut Light, &mut LightPulse)>, time: Res<GameTime>,
+    mut rng: ResMut<GameRng>,
+) {
     for (mut light, mut pulse) in entities.iter_mut() {
         if pulse
             .state
@@ -81,7 +85,7 @@ fn light_pulse(mut entities: Query<(&mut Light, &mut LightPulse)>, time: Res<Gam
             .unwrap_or(true)
         {
             use rand::*;
-            let period = pulse.period.mul_f32(thread_rng().gen_range(0.8..1.2));
+            let period = pulse.period.mul_f32(rng.cosmetic().gen_range(0.8..1.2));
             pulse.state = Some((time.now(), period));
         }
         let (start, period) = pulse.state.unwrap();
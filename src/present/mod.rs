@@ -4,6 +4,7 @@ pub mod camera;
 pub mod depth;
 pub mod effect;
 pub mod hud_elements;
+pub mod interpolation;
 pub mod light;
 pub mod sound;
 
@@ -17,5 +18,8 @@ impl Plugin for PresentationPlugin {
             .add_plugin(sound::SoundPlugin)
             .add_plugin(effect::EffectPlugin)
             .add_plugin(hud_elements::HudElementsPlugin);
+        if !app.is_headless() {
+            app.add_plugin(interpolation::InterpolationPlugin);
+        }
     }
 }
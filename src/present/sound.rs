@@ -26,14 +26,14 @@ pub struct Beats {
 }
 
 impl Beats {
-    pub fn in_beat(&self, time: &Time) -> bool {
+    pub fn in_beat(&self, time: &GameTime) -> bool {
         let allow_before = 0.12;
         let allow_after = 0.13;
 
         match self.start {
             Some(start) => {
                 let period = self.period.as_secs_f32();
-                let at = (time.time_since_startup() - start).as_secs_f32() % period;
+                let at = time.passed_real(start).as_secs_f32() % period;
                 at < allow_after || at > period - allow_before
             }
             None => false,
@@ -47,10 +47,14 @@ pub struct SoundPlugin;
 
 impl Plugin for SoundPlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<Beats>()
+            .add_event::<Sound>()
+            .add_game_system(GameStage::First, beat_clock);
+        if app.is_headless() {
+            return;
+        }
         app.add_plugin(bevy_kira_audio::AudioPlugin)
             .init_resource::<ListenerConfig>()
-            .init_resource::<Beats>()
-            .add_event::<Sound>()
             .add_system(apply_settings)
             .add_system(update_listener_config)
             .add_system(play_sounds)
@@ -126,7 +130,7 @@ fn update_listener_config(
 
 fn play_sounds(
     mut events: EventReader<Sound>, audio: Res<Audio>, config: Res<ListenerConfig>,
-    mut commands: Commands, time_mode: Res<TimeMode>,
+    mut commands: Commands, time_mode: Res<TimeMode>, mut rng: ResMut<GameRng>,
 ) {
     let leading_silence = 0.25; // TODO: this is atrocious hack since bevy_kira_audio doesn't expose kira's start time
 
@@ -145,7 +149,7 @@ fn play_sounds(
         let mut cmd = audio.play(event.sound.clone());
         if !event.non_randomized {
             cmd.with_playback_rate(
-                thread_rng().gen_range(0.9..1.2) * time_mode.overriden.unwrap_or(1.) as f64,
+                rng.cosmetic().gen_range(0.9..1.2) * time_mode.overriden.unwrap_or(1.) as f64,
             );
         }
         cmd.with_volume(volume * K_VOLUME)
@@ -205,10 +209,8 @@ fn menu_drone(
     }
 }
 
-fn beats(
-    mut beats: ResMut<Beats>, time: Res<Time>, time_mode: Res<TimeMode>, audio: Res<Audio>,
-    assets: Res<MyAssets>,
-) {
+/// Beats affect the gameplay, so they're counted in ticks
+fn beat_clock(mut beats: ResMut<Beats>, time: Res<GameTime>, time_mode: Res<TimeMode>) {
     if beats.level != 0 && !time_mode.stopped() {
         beats.period = match beats.level {
             1 => Duration::from_millis(1000),
@@ -216,21 +218,22 @@ fn beats(
         };
         let initial_delay = Duration::from_millis(300);
 
-        let start = *beats
-            .start
-            .get_or_insert(time.time_since_startup() + initial_delay);
-        match time.time_since_startup().checked_sub(start) {
-            Some(passed) => {
-                let count = (passed.as_micros() / beats.period.as_micros()) as i32;
-                if count != beats.count {
-                    beats.count = count;
-                    audio.play(assets.beat.clone());
-                }
-            }
-            None => (),
+        let start = *beats.start.get_or_insert(time.real_now() + initial_delay);
+        if let Some(passed) = time.real_now().checked_sub(start) {
+            beats.count = (passed.as_micros() / beats.period.as_micros()) as i32;
         }
     } else {
         beats.start = None;
         beats.count = 0;
     }
 }
+
+fn beats(beats: Res<Beats>, mut played: Local<i32>, audio: Res<Audio>, assets: Res<MyAssets>) {
+    if beats.count != *played {
+        *played = beats.count;
+        // counter is reset to zero when beats stop
+        if beats.count != 0 {
+            audio.play(assets.beat.clone());
+        }
+    }
+}
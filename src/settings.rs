@@ -1,4 +1,4 @@
-use crate::common::*;
+use crate::{common::*, storage::Storage};
 pub use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
@@ -6,9 +6,17 @@ pub struct Settings {
     pub master_volume: f32,
     pub fullscreen: bool,
     pub difficulty: Difficulty,
+    /// Overrides difficulty for the current run (continued or replayed), reset when it's stopped
+    #[serde(skip)]
+    pub run_difficulty: Option<Difficulty>,
 }
 
 impl Settings {
+    /// Difficulty of the current run
+    pub fn difficulty(&self) -> Difficulty {
+        self.run_difficulty.unwrap_or(self.difficulty)
+    }
+
     pub fn menu(&mut self, ui: &mut egui::Ui) {
         let mut changed = false;
 
@@ -47,99 +55,32 @@ impl Default for Settings {
             master_volume: 0.6,
             fullscreen: false,
             difficulty: Difficulty::Hard,
+            run_difficulty: None,
         }
     }
 }
 
-#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug)]
 pub enum Difficulty {
     Easy,
     Hard,
 }
 
-// desktop
-#[cfg(not(target_arch = "wasm32"))]
-impl Settings {
-    const FILE: &'static str = "user.cfg.ron";
-
-    pub fn save(&self) {
-        match std::fs::write(Self::FILE, self.save_ron()) {
-            Ok(_) => (),
-            Err(error) => log::error!(
-                "Failed to save settings (file: \"{}\") - {}",
-                Self::FILE,
-                error
-            ),
-        }
-    }
-
-    pub fn load() -> Option<Self> {
-        Self::load_ron(&std::fs::read_to_string(Self::FILE).ok()?)
-    }
-}
-
-// wasm
-#[cfg(target_arch = "wasm32")]
 impl Settings {
-    const NAME: &'static str = "blobfight-settings";
-    const EXPIRE: Duration = Duration::from_secs(60 * 60 * 24 * 30); // 30 days
+    const STORAGE: Storage = Storage {
+        name: "settings",
+        file: "user.cfg.ron",
+        cookie: "blobfight-settings",
+    };
 
     pub fn save(&self) {
-        wasm_cookies::set(
-            Self::NAME,
-            &Self::encode(self.save_ron().as_bytes()),
-            &wasm_cookies::CookieOptions::default().expires_after(Self::EXPIRE),
-        );
+        Self::STORAGE.save(&self.save_ron())
     }
 
     pub fn load() -> Option<Self> {
-        match wasm_cookies::get(Self::NAME) {
-            Some(Ok(data)) => Self::load_ron(std::str::from_utf8(&Self::decode(&data)?).ok()?),
-            Some(Err(error)) => {
-                log::warn!("cookie read error: {}", error);
-                None
-            }
-            None => None,
-        }
-    }
-
-    // base64 or something similiar
-    fn encode(data: &[u8]) -> String {
-        data.into_iter()
-            .flat_map(|c| [c >> 4, c & 15])
-            .map(|c| char::from_digit(c.into(), 16).unwrap())
-            .fold(String::with_capacity(data.len() * 2), |mut s, c| {
-                s.push(c);
-                s
-            })
-    }
-    fn decode(data: &str) -> Option<Vec<u8>> {
-        use itertools::Itertools;
-        if data.len() % 2 != 0 {
-            log::error!("Invalid length");
-            return None;
-        }
-        data.chars()
-            .tuples()
-            .try_fold(
-                Vec::with_capacity(data.len() / 2),
-                |mut s, (c1, c2)| match char::to_digit(c1, 16).zip(char::to_digit(c2, 16)) {
-                    Some((n1, n2)) => {
-                        let n = (n1 << 4) | n2;
-                        s.push(n as u8);
-                        Ok(s)
-                    }
-                    None => {
-                        log::error!("Invalid character");
-                        Err(())
-                    }
-                },
-            )
-            .ok()
+        Self::load_ron(&Self::STORAGE.load()?)
     }
-}
 
-impl Settings {
     fn save_ron(&self) -> String {
         ron::ser::to_string(self).unwrap()
     }
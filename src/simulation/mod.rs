@@ -0,0 +1,22 @@
+use crate::common::*;
+
+#[cfg(test)]
+mod runner;
+#[cfg(test)]
+mod tests;
+
+/// Resource - app runs without window, rendering and audio.
+/// Must be inserted before any of the game plugins are added.
+#[cfg_attr(not(test), allow(dead_code))] // only simulation uses it
+pub struct Headless;
+
+pub trait AppHeadless {
+    /// Plugins must not add systems which require window, rendering or audio if this is true
+    fn is_headless(&self) -> bool;
+}
+
+impl AppHeadless for App {
+    fn is_headless(&self) -> bool {
+        self.world.contains_resource::<Headless>()
+    }
+}
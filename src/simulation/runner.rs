@@ -0,0 +1,128 @@
+use super::Headless;
+use crate::{
+    common::*,
+    control::input::InputAction,
+    objects::{
+        player::Player,
+        spawn::SpawnControl,
+        waves::{WaveDefinition, WaveList},
+    },
+    present::camera::WindowInfo,
+};
+use bevy::{ecs::event::Events, utils::Instant};
+
+/// Headless game with fixed timestep, for automated gameplay tests
+pub struct Simulation {
+    pub app: App,
+    instant: Instant,
+}
+
+/// Nothing is spawned until `start` is called
+impl Default for Simulation {
+    fn default() -> Self {
+        let mut app = App::new();
+        app.insert_resource(Headless)
+            .add_plugin(bevy::core::CorePlugin)
+            .add_plugin(bevy::transform::TransformPlugin)
+            .add_plugin(bevy::hierarchy::HierarchyPlugin)
+            .add_plugin(bevy::input::InputPlugin)
+            .add_plugin(bevy::asset::AssetPlugin)
+            .insert_resource(Time::default())
+            .insert_resource(Settings::default())
+            .insert_resource(GameRng::with_seed(0));
+        crate::add_game_plugins(&mut app);
+
+        let mut sim = Self {
+            app,
+            instant: Instant::now(),
+        };
+        sim.step(1); // run startup systems
+        sim
+    }
+}
+
+impl Simulation {
+    /// Exactly one gameplay tick is run per frame, unless `step_slow` is used
+    pub const DELTA: Duration = GameTime::TICK;
+
+    pub fn world(&mut self) -> &mut World {
+        &mut self.app.world
+    }
+
+    /// Replaces the campaign with the single wave
+    pub fn load_wave(&mut self, wave: WaveDefinition) {
+        let world = self.world();
+        let handle = world
+            .resource_mut::<Assets<WaveList>>()
+            .add(WaveList { waves: vec![wave] });
+        world.resource_mut::<MyAssets>().waves = handle;
+    }
+
+    /// Spawns the first wave
+    pub fn start(&mut self) {
+        self.world().resource_mut::<SpawnControl>().despawn = Some(true);
+
+        // assets are loaded in background
+        for _ in 0..1000 {
+            self.step(1);
+            if self.world().resource::<SpawnControl>().is_game_running() {
+                // let commands and physics settle
+                self.step(2);
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        panic!("Game didn't start - waves are not loaded?")
+    }
+
+    /// Runs exactly that number of frames, each one `DELTA` long
+    pub fn step(&mut self, frames: usize) {
+        self.step_slow(frames, 1)
+    }
+
+    /// Runs that number of frames, each one long enough for several ticks,
+    /// like when rendering can't keep up with the gameplay
+    pub fn step_slow(&mut self, frames: usize, ticks_per_frame: u32) {
+        for _ in 0..frames {
+            self.instant += Self::DELTA * ticks_per_frame;
+            let instant = self.instant;
+            self.world()
+                .resource_mut::<Time>()
+                .update_with_instant(instant);
+            self.app.update();
+        }
+    }
+
+    /// Action is received only during the next frame
+    pub fn send(&mut self, action: InputAction) {
+        self.world()
+            .resource_mut::<Events<InputAction>>()
+            .send(action)
+    }
+
+    /// Send entity command event, like `KinematicCommand`
+    pub fn command<T: 'static + Send + Sync>(&mut self, entity: Entity, command: T) {
+        self.world()
+            .resource_mut::<Events<(Entity, T)>>()
+            .send((entity, command))
+    }
+
+    /// World position
+    pub fn set_cursor(&mut self, pos: Vec2) {
+        self.world().resource_mut::<WindowInfo>().cursor = pos
+    }
+
+    pub fn player(&mut self) -> Option<Entity> {
+        self.world()
+            .query_filtered::<Entity, With<Player>>()
+            .iter(&self.app.world)
+            .next()
+    }
+
+    pub fn position(&mut self, entity: Entity) -> Vec2 {
+        self.world()
+            .get::<GlobalTransform>(entity)
+            .map(|transform| transform.pos_2d())
+            .expect("Entity has no position")
+    }
+}
@@ -0,0 +1,225 @@
+use super::runner::Simulation;
+use crate::{
+    common::*,
+    control::input::InputAction,
+    mechanics::{
+        damage::{ForceField, SmallProjectile, Team},
+        health::Health,
+        movement::KinematicCommand,
+    },
+    objects::{
+        boss::TheBoss,
+        spawn::{SpawnControl, TurretType},
+        stats::Stats,
+        waves::{BossPlacement, TurretDefinition, WaveDefinition},
+        weapon::{CraftedWeapon, WeaponInstance},
+    },
+};
+use bevy::utils::HashSet;
+
+fn single_turret(ty: TurretType) -> Simulation {
+    let mut sim = Simulation::default();
+    sim.load_wave(WaveDefinition {
+        turrets: vec![TurretDefinition {
+            pos: vec2(0., 0.6),
+            ty,
+            loot: None,
+            movement: None,
+        }],
+        ..default()
+    });
+    sim.start();
+    sim
+}
+
+fn enemies(sim: &mut Simulation) -> Vec<Entity> {
+    sim.world()
+        .query::<(Entity, &Team, &Health)>()
+        .iter(&sim.app.world)
+        .filter(|(_, team, _)| matches!(team, Team::Enemy))
+        .map(|(entity, ..)| entity)
+        .collect()
+}
+
+#[test]
+fn wave_starts() {
+    let mut sim = single_turret(TurretType::Simple);
+    sim.step(10);
+
+    assert!(sim.player().is_some());
+    assert_eq!(enemies(&mut sim).len(), 1);
+
+    let spawn = sim.world().resource::<SpawnControl>();
+    assert_eq!(spawn.wave_spawned, Some(0));
+    assert!(!spawn.waiting_for_next_wave);
+}
+
+#[test]
+fn railgun_kills_simple_turret_in_one_powered_shot() {
+    let mut sim = single_turret(TurretType::Simple);
+    let player = sim.player().unwrap();
+    let turret = enemies(&mut sim)[0];
+
+    let points = sim.world().resource::<Stats>().player.points;
+    assert!(matches!(
+        sim.world().resource::<Stats>().player.weapon0,
+        Some(WeaponInstance { weapon: CraftedWeapon::Railgun, uses, .. }) if uses == 3.
+    ));
+
+    // shooting in direction of the dash is powered
+    let target = sim.position(turret);
+    let dir = (target - sim.position(player)).normalize();
+    sim.set_cursor(target);
+    sim.command(player, KinematicCommand::Dash { dir });
+    sim.send(InputAction::FireMega);
+    sim.step(10);
+
+    assert!(sim.world().get_entity(turret).is_none());
+    assert!(enemies(&mut sim).is_empty());
+
+    let stats = sim.world().resource::<Stats>();
+    assert!(stats.player.points > points);
+    assert!(matches!(
+        stats.player.weapon0,
+        Some(WeaponInstance { weapon: CraftedWeapon::Railgun, uses, .. }) if uses == 2.
+    ));
+
+    sim.step(2);
+    assert!(sim.world().resource::<SpawnControl>().waiting_for_next_wave);
+}
+
+#[test]
+fn boss_shots_pass_through_its_own_shields() {
+    let mut sim = Simulation::default();
+    sim.load_wave(WaveDefinition {
+        boss: Some(BossPlacement {
+            pos: vec2(0., 0.5),
+            id: "shield_robot".to_string(),
+        }),
+        ..default()
+    });
+    sim.start();
+
+    let player = sim.player().unwrap();
+    sim.world().get_mut::<Health>(player).unwrap().invincible = true;
+
+    let boss = sim
+        .world()
+        .query_filtered::<Entity, With<TheBoss>>()
+        .iter(&sim.app.world)
+        .next()
+        .unwrap();
+    let origin = sim.position(boss);
+    // shield ring is between these distances from the core
+    let (inner, outer) = (2., 3.);
+
+    let mut inside = HashSet::default();
+    let mut passed = false;
+    for _ in 0..600 {
+        sim.step(1);
+        for (entity, pos, team) in sim
+            .world()
+            .query_filtered::<(Entity, &GlobalTransform, &Team), With<SmallProjectile>>()
+            .iter(&sim.app.world)
+        {
+            if !matches!(team, Team::Enemy) {
+                continue;
+            }
+            let distance = pos.pos_2d().distance(origin);
+            if distance < inner {
+                inside.insert(entity);
+            } else if distance > outer && inside.contains(&entity) {
+                passed = true;
+            }
+        }
+        if passed {
+            break;
+        }
+    }
+    assert!(!inside.is_empty(), "Boss core didn't shoot");
+    assert!(passed, "Boss shots were stopped by its own shields");
+}
+
+#[test]
+fn player_projectiles_pass_through_own_force_field() {
+    let mut sim = Simulation::default();
+    sim.load_wave(default());
+    sim.start();
+
+    let player = sim.player().unwrap();
+    {
+        let mut stats = sim.world().resource_mut::<Stats>();
+        stats.player.weapon0 = Some(WeaponInstance::new(CraftedWeapon::Shield, 3.));
+        stats.player.weapon1 = Some(WeaponInstance::new(CraftedWeapon::Plasma, 3.));
+    }
+    let health = sim.world().get::<Health>(player).unwrap().value;
+
+    // plasma ball is shot through the field which is right in front of the player
+    let target = sim.position(player) + vec2(0., 10.);
+    sim.set_cursor(target);
+    sim.send(InputAction::FireMega);
+    sim.step(2);
+    assert!(
+        sim.world()
+            .query_filtered::<(), With<ForceField>>()
+            .iter(&sim.app.world)
+            .next()
+            .is_some(),
+        "Force field wasn't created"
+    );
+
+    sim.send(InputAction::ChangeWeapon);
+    sim.step(1);
+    sim.send(InputAction::FireMega);
+    sim.step(30);
+
+    assert_eq!(sim.world().get::<Health>(player).unwrap().value, health);
+}
+
+#[test]
+fn repeller_works_when_frame_runs_several_ticks() {
+    let mut sim = single_turret(TurretType::Simple);
+    let player = sim.player().unwrap();
+    sim.world().get_mut::<Health>(player).unwrap().invincible = true;
+    sim.world().resource_mut::<Stats>().player.weapon0 =
+        Some(WeaponInstance::new(CraftedWeapon::Repeller, 3.));
+
+    // away from the world origin, so a wrong one is noticed
+    for _ in 0..60 {
+        sim.command(
+            player,
+            KinematicCommand::Move {
+                dir: vec2(1., -1.).normalize(),
+            },
+        );
+        sim.step(1);
+    }
+    assert!(sim.position(player).length() > 5.);
+
+    let mut incoming = None;
+    for _ in 0..600 {
+        sim.step(1);
+        let pos = sim.position(player);
+        incoming = sim
+            .world()
+            .query_filtered::<(Entity, &GlobalTransform, &Team), With<SmallProjectile>>()
+            .iter(&sim.app.world)
+            .find(|(_, projectile, team)| {
+                let distance = projectile.pos_2d().distance(pos);
+                matches!(team, Team::Enemy) && distance > 1.5 && distance < 3.
+            })
+            .map(|(entity, ..)| entity);
+        if incoming.is_some() {
+            break;
+        }
+    }
+    let incoming = incoming.expect("Turret didn't shoot at the player");
+
+    sim.send(InputAction::FireMega);
+    sim.step_slow(1, 5);
+
+    assert!(matches!(
+        sim.world().get::<Team>(incoming),
+        Some(Team::Player)
+    ));
+}
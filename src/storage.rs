@@ -0,0 +1,121 @@
+use crate::common::*;
+
+/// Persistent text data - file on desktop, cookie on wasm
+pub struct Storage {
+    /// Used only for logging
+    pub name: &'static str,
+    pub file: &'static str,
+    pub cookie: &'static str,
+}
+
+// desktop
+#[cfg(not(target_arch = "wasm32"))]
+impl Storage {
+    pub fn save(&self, data: &str) {
+        match std::fs::write(self.file, data) {
+            Ok(_) => (),
+            Err(error) => log::error!(
+                "Failed to save {} (file: \"{}\") - {}",
+                self.name,
+                self.file,
+                error
+            ),
+        }
+    }
+
+    pub fn load(&self) -> Option<String> {
+        std::fs::read_to_string(self.file).ok()
+    }
+
+    pub fn remove(&self) {
+        match std::fs::remove_file(self.file) {
+            Ok(_) => (),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => (),
+            Err(error) => log::error!(
+                "Failed to remove {} (file: \"{}\") - {}",
+                self.name,
+                self.file,
+                error
+            ),
+        }
+    }
+}
+
+// wasm
+#[cfg(target_arch = "wasm32")]
+impl Storage {
+    const EXPIRE: Duration = Duration::from_secs(60 * 60 * 24 * 30); // 30 days
+
+    pub fn save(&self, data: &str) {
+        wasm_cookies::set(
+            self.cookie,
+            &Self::encode(data.as_bytes()),
+            &wasm_cookies::CookieOptions::default().expires_after(Self::EXPIRE),
+        );
+    }
+
+    pub fn remove(&self) {
+        wasm_cookies::delete(self.cookie)
+    }
+
+    pub fn load(&self) -> Option<String> {
+        match wasm_cookies::get(self.cookie) {
+            Some(Ok(data)) => String::from_utf8(Self::decode(&data)?).ok(),
+            Some(Err(error)) => {
+                log::warn!("cookie read error ({}): {}", self.name, error);
+                None
+            }
+            None => None,
+        }
+    }
+
+    // base64 or something similiar
+    fn encode(data: &[u8]) -> String {
+        data.into_iter()
+            .flat_map(|c| [c >> 4, c & 15])
+            .map(|c| char::from_digit(c.into(), 16).unwrap())
+            .fold(String::with_capacity(data.len() * 2), |mut s, c| {
+                s.push(c);
+                s
+            })
+    }
+    fn decode(data: &str) -> Option<Vec<u8>> {
+        use itertools::Itertools;
+        if data.len() % 2 != 0 {
+            log::error!("Invalid length");
+            return None;
+        }
+        data.chars()
+            .tuples()
+            .try_fold(
+                Vec::with_capacity(data.len() / 2),
+                |mut s, (c1, c2)| match char::to_digit(c1, 16).zip(char::to_digit(c2, 16)) {
+                    Some((n1, n2)) => {
+                        let n = (n1 << 4) | n2;
+                        s.push(n as u8);
+                        Ok(s)
+                    }
+                    None => {
+                        log::error!("Invalid character");
+                        Err(())
+                    }
+                },
+            )
+            .ok()
+    }
+}
+
+/// Seconds since UNIX epoch
+pub fn unix_time() -> u64 {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default()
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        (js_sys::Date::now() / 1000.) as u64
+    }
+}
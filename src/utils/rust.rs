@@ -16,24 +16,23 @@ pub trait RandomSelect<T> {
     /// Returns random element.
     ///
     /// Panics if there are no elements to select from.
-    fn random(self) -> T;
+    fn random(self, rng: &mut impl rand::Rng) -> T;
 
     /// Returns random element or None if there are no elements to select from
-    fn get_random(self) -> Option<T>;
+    fn get_random(self, rng: &mut impl rand::Rng) -> Option<T>;
 }
 
 impl<Iter: ExactSizeIterator + Clone> RandomSelect<Iter::Item> for Iter {
-    fn random(self) -> Iter::Item {
-        self.get_random().unwrap()
+    fn random(self, rng: &mut impl rand::Rng) -> Iter::Item {
+        self.get_random(rng).unwrap()
     }
 
-    fn get_random(self) -> Option<Iter::Item> {
-        use rand::*;
+    fn get_random(self, rng: &mut impl rand::Rng) -> Option<Iter::Item> {
         let len = self.len();
         if len == 0 {
             None
         } else {
-            let i = thread_rng().gen_range(0..len);
+            let i = rng.gen_range(0..len);
             self.skip(i).next()
         }
     }